@@ -0,0 +1,46 @@
+use crate::error::{Error, Result};
+use crate::models::WaterDetail;
+
+// Saved copies of fetched pages (see --cache-dir), one file per page named <is_number>_<ws_number>.html.
+// Pages found here are parsed without sending a request, so the same pages can be scraped again while working on the parser.
+#[derive(Clone)]
+pub struct PageCache {
+    dir: std::path::PathBuf,
+    is_refreshing: bool // Set by --no-cache. Every page is fetched again, and the cached copy replaced.
+}
+
+impl PageCache {
+    // Creates the cache directory if it doesn't exist yet
+    pub fn open(dir: &std::path::Path, is_refreshing: bool) -> Result<PageCache> {
+        std::fs::create_dir_all(dir).map_err(|source| Error::CacheDir { path: dir.to_path_buf(), source })?;
+        Ok(PageCache { dir: dir.to_path_buf(), is_refreshing })
+    }
+
+    // The cached page of a water detail, unless there is none or it's being refreshed
+    pub fn load(& self, detail: &WaterDetail) -> Option<String> {
+        if self.is_refreshing {
+            return None
+        }
+        let page_path: std::path::PathBuf = self.page_path(detail);
+        match std::fs::read(&page_path) {
+            Ok(html) => Some(String::from_utf8_lossy(&html).into_owned()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("Unable to read cached page {}. Fetching it instead. {}", page_path.display(), e);
+                None
+            }
+        }
+    }
+
+    // A page that can't be saved is only warned about, since the page itself was still fetched
+    pub fn save(& self, detail: &WaterDetail, html: &str) {
+        let page_path: std::path::PathBuf = self.page_path(detail);
+        if let Err(e) = std::fs::write(&page_path, html) {
+            log::warn!("Unable to save page {} to the cache. {}", page_path.display(), e);
+        }
+    }
+
+    fn page_path(& self, detail: &WaterDetail) -> std::path::PathBuf {
+        self.dir.join(format!("{}_{}.html", detail.is_number.as_deref().unwrap_or_default(), detail.ws_number))
+    }
+}
@@ -0,0 +1,1226 @@
+use clap::{arg, value_parser, Command, ArgAction, ArgMatches};
+use clap::parser::ValueSource;
+
+use crate::error::{Error, Result};
+use crate::input::{DuplicateColumns, SampleSize};
+use crate::parse::Direction;
+use crate::parse::SOURCE_WATER_CATEGORIES;
+use crate::output::OUTPUT_FIELDS;
+
+// Every setting that can be passed on the command line
+#[derive(Debug)]
+pub struct Config {
+    pub input_file_paths: Vec<std::path::PathBuf>,
+    pub is_json_input: bool,
+    pub is_url_input: bool, // One water system detail page URL per line
+    pub input_delimiter: Option<u8>, // None means the delimiter is sniffed from each input file
+    pub output: OutputTarget,
+    pub is_json_output: bool,
+    pub is_append_output: bool, // Add to an existing output file instead of replacing it
+    pub output_delimiter: u8,
+    pub output_quote_style: csv::QuoteStyle,
+    pub output_bom: bool,
+    pub output_fields: Vec<&'static str>, // Empty means every column is written
+    pub delay: u32,
+    pub delay_jitter: u32,
+    pub min_delay: Option<u32>,
+    pub max_delay: Option<u32>,
+    pub is_header: String,
+    pub st_header: String,
+    pub ws_header: String,
+    pub max_requests: Option<u32>,
+    pub rate: Option<u32>,
+    pub print_parsed: bool,
+    pub no_cache: bool,
+    pub active_only: bool,
+    pub population_min: Option<u64>,
+    pub since: Option<chrono::NaiveDate>, // None means there is no earliest last updated date
+    pub until: Option<chrono::NaiveDate>, // None means there is no latest last updated date
+    pub progress_json: bool,
+    pub duplicate_columns: DuplicateColumns,
+    pub log_level: log::LevelFilter,
+    pub max_redirects: usize,
+    pub user_agent: String,
+    pub proxy: Option<String>, // None connects to the website directly
+    pub system_types: Vec<String>, // Empty means every type is scraped
+    pub skip_empty_buyers: bool,
+    pub direction: Direction,
+    pub input_encoding: &'static encoding_rs::Encoding,
+    pub states: Vec<String>, // Empty means rows from every state are scraped
+    pub limit: Option<usize>, // None means every input row is scraped
+    pub sample: Option<SampleSize>, // None means every input row is scraped
+    pub sample_seed: Option<u64>,
+    pub store_html: bool,
+    pub cache_dir: Option<std::path::PathBuf>,
+    pub manifest: Option<std::path::PathBuf>,
+    pub fail_fast: bool,
+    pub only_new: bool,
+    pub db_path: std::path::PathBuf,
+    pub fast_db: bool,
+    pub upsert: bool,
+    pub retries: u32,
+    pub timeout: u64,
+    pub resume: bool,
+    pub dry_run: bool, // Nothing is written to the database or the output
+    pub jobs: u32, // Pages fetched at once
+    pub max_depth: u32 // Hops of buyers followed from the input rows. 0 only scrapes the input rows.
+}
+
+// Settings for the reparse subcommand
+#[derive(Debug)]
+pub struct ReparseConfig {
+    pub from_html: Option<std::path::PathBuf>, // None means pages are read from the page_html table
+    pub db_path: std::path::PathBuf,
+    pub log_level: log::LevelFilter
+}
+
+// Settings for the export subcommand
+#[derive(Debug)]
+pub struct ExportConfig {
+    pub table: ExportTable,
+    pub output: OutputTarget, // Never Disabled
+    pub is_json_output: bool,
+    pub output_delimiter: u8,
+    pub db_path: std::path::PathBuf,
+    pub log_level: log::LevelFilter
+}
+
+// Which table of the database to export
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportTable {
+    Details,
+    Relationships
+}
+
+// What the scraper was asked to do
+#[derive(Debug)]
+pub enum Action {
+    Scrape(Box<Config>),
+    Reparse(ReparseConfig),
+    Export(ExportConfig)
+}
+
+// Parses the command line arguments. Exits with usage info if they are malformed,
+// and returns an error if they point at files that can't be used.
+pub fn parse_args() -> Result<Action> {
+    parse_args_from(std::env::args_os())
+}
+
+// Same as parse_args, but for the given arguments instead of the process's. The first one is the program name.
+pub fn parse_args_from<T: Into<std::ffi::OsString> + Clone>(args: impl IntoIterator<Item = T>) -> Result<Action> {
+    let arg_matches = command().get_matches_from(args);
+    match arg_matches.subcommand() {
+        Some(("reparse", reparse_matches)) => Ok(Action::Reparse(ReparseConfig::from_matches(reparse_matches)?)),
+        Some(("export", export_matches)) => Ok(Action::Export(ExportConfig::from_matches(export_matches)?)),
+        _ => Ok(Action::Scrape(Box::new(Config::from_matches(&arg_matches)?)))
+    }
+}
+
+impl ReparseConfig {
+    fn from_matches(arg_matches: &ArgMatches) -> Result<ReparseConfig> {
+        let from_html: Option<std::path::PathBuf> = 
+            arg_matches
+                .get_one::<String>("from_html")
+                .map(|dir| {
+                    let html_dir: std::path::PathBuf = std::fs::canonicalize(dir)
+                        .map_err(|source| Error::InputIo { path: std::path::PathBuf::from(dir), source })?;
+                    if !html_dir.is_dir() {
+                        return Err(Error::Config(format!("{} is not a directory.", html_dir.display())))
+                    }
+                    Ok(html_dir)
+                })
+                .transpose()?;
+        let db_path: std::path::PathBuf = resolve_db_path(arg_matches.get_one::<String>("db").expect("db is missing a default value."))?;
+        let log_level: log::LevelFilter = log_level(arg_matches.get_count("verbose"), arg_matches.get_flag("quiet"));
+        Ok(ReparseConfig { from_html, db_path, log_level })
+    }
+}
+
+impl ExportConfig {
+    fn from_matches(arg_matches: &ArgMatches) -> Result<ExportConfig> {
+        let table: ExportTable = 
+            match arg_matches.get_one::<String>("table").expect("table is missing a default value.").as_str() {
+                "details" => ExportTable::Details,
+                _ => ExportTable::Relationships
+            };
+        let is_json_output: bool = arg_matches.get_one::<String>("format").is_some_and(|format| format == "json");
+        let output_delimiter: u8 = *arg_matches.get_one::<u8>("output_delimiter").expect("output_delimiter is missing a default value.");
+        // "-" writes the export to stdout
+        let output_arg: &String = arg_matches.get_one::<String>("output").expect("output is missing a default value.");
+        let output: OutputTarget = 
+            if output_arg == "-" {
+                OutputTarget::Stdout
+            }
+            else {
+                OutputTarget::File(
+                    std::path::absolute(std::path::Path::new(output_arg))
+                        .map_err(|source| Error::OutputFile { path: std::path::PathBuf::from(output_arg), source })?
+                )
+            };
+        let db_path: std::path::PathBuf = resolve_db_path(arg_matches.get_one::<String>("db").expect("db is missing a default value."))?;
+        let log_level: log::LevelFilter = log_level(arg_matches.get_count("verbose"), arg_matches.get_flag("quiet"));
+        Ok(ExportConfig { table, output, is_json_output, output_delimiter, db_path, log_level })
+    }
+}
+
+// Where the output csv goes
+#[derive(Debug)]
+pub enum OutputTarget {
+    File(std::path::PathBuf),
+    Stdout,
+    Disabled // Only the database is written
+}
+
+// Defaults loaded from a --config file. Keys are the long flag names (i.e., max-requests = 100).
+#[derive(Default, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigFile {
+    pub input: Option<Vec<String>>,
+    pub input_format: Option<String>,
+    pub input_delimiter: Option<String>,
+    pub output: Option<String>,
+    pub format: Option<String>,
+    pub write_mode: Option<String>,
+    pub delay: Option<u32>,
+    pub delay_jitter: Option<u32>,
+    pub min_delay: Option<u32>,
+    pub max_delay: Option<u32>,
+    #[serde(rename = "header_ws")]
+    pub header_ws: Option<String>,
+    #[serde(rename = "header_is")]
+    pub header_is: Option<String>,
+    #[serde(rename = "header_state")]
+    pub header_state: Option<String>,
+    pub max_requests: Option<u32>,
+    pub output_delimiter: Option<String>,
+    pub output_quote_style: Option<String>,
+    pub output_bom: Option<bool>,
+    pub fields: Option<Vec<String>>,
+    pub rate: Option<u32>,
+    pub print_parsed: Option<bool>,
+    pub no_cache: Option<bool>,
+    pub active_only: Option<bool>,
+    pub population_min: Option<u64>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub progress_json: Option<bool>,
+    pub pick_first: Option<bool>,
+    pub pick_last: Option<bool>,
+    pub verbose: Option<u8>,
+    pub quiet: Option<bool>,
+    pub max_redirects: Option<usize>,
+    pub user_agent: Option<String>,
+    pub proxy: Option<String>,
+    pub system_type: Option<Vec<String>>,
+    pub skip_empty_buyers: Option<bool>,
+    pub direction: Option<String>,
+    pub no_default_output: Option<bool>,
+    pub input_encoding: Option<String>,
+    pub state: Option<Vec<String>>,
+    pub limit: Option<usize>,
+    pub sample: Option<usize>,
+    pub sample_fraction: Option<f64>,
+    pub sample_seed: Option<u64>,
+    pub store_html: Option<bool>,
+    pub cache_dir: Option<String>,
+    pub manifest: Option<String>,
+    pub fail_fast: Option<bool>,
+    pub only_new: Option<bool>,
+    pub db: Option<String>,
+    pub fast_db: Option<bool>,
+    pub upsert: Option<bool>,
+    pub retries: Option<u32>,
+    pub timeout: Option<u64>,
+    pub resume: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub jobs: Option<u32>,
+    pub max_depth: Option<u32>
+}
+
+impl ConfigFile {
+    pub fn load(path: &std::path::Path) -> Result<ConfigFile> {
+        let contents: String = 
+            std::fs::read_to_string(path)
+                .map_err(|e| Error::Config(format!("Unable to read config file {}. {}", path.display(), e)))?;
+        toml::from_str(&contents)
+            .map_err(|e| Error::Config(format!("Config file {} is not valid. {}", path.display(), e)))
+    }
+}
+
+impl Config {
+    // The wait before the next request, kept within --min-delay and --max-delay
+    pub fn request_delay(& self) -> u32 {
+        self.delay.clamp(self.min_delay.unwrap_or(0), self.max_delay.unwrap_or(u32::MAX))
+    }
+
+    // The most that --delay-jitter may add to the wait, cut short so the wait never goes past --max-delay
+    pub fn request_jitter(& self) -> u32 {
+        self.delay_jitter.min(self.max_delay.unwrap_or(u32::MAX).saturating_sub(self.request_delay()))
+    }
+
+    // Arguments given on the command line take priority over the config file, which takes priority over the defaults.
+    fn from_matches(arg_matches: &ArgMatches) -> Result<Config> {
+        let config_file: ConfigFile = 
+            match arg_matches.get_one::<String>("config") {
+                Some(path) => ConfigFile::load(std::path::Path::new(path))?,
+                None => ConfigFile::default()
+            };
+        // Values from the config file get the same checks as the command line
+        let file_input_format: Option<String> = config_file.input_format.map(|f| check_choice("input-format", f, INPUT_FORMATS)).transpose()?;
+        let file_output_format: Option<String> = config_file.format.map(|f| check_choice("format", f, OUTPUT_FORMATS)).transpose()?;
+        let file_direction: Option<String> = config_file.direction.map(|d| check_choice("direction", d, DIRECTIONS)).transpose()?;
+        let file_write_mode: Option<String> = config_file.write_mode.map(|m| check_choice("write-mode", m, WRITE_MODES)).transpose()?;
+        let file_output_quote_style: Option<String> = config_file.output_quote_style.map(|q| check_choice("output-quote-style", q, OUTPUT_QUOTE_STYLES)).transpose()?;
+        let file_input_delimiter: Option<Option<u8>> = config_file.input_delimiter.map(|d| parse_input_delimiter(&d)).transpose().map_err(Error::Config)?;
+        let file_input_encoding: Option<&'static encoding_rs::Encoding> = config_file.input_encoding.map(|e| parse_encoding(&e)).transpose().map_err(Error::Config)?;
+        let file_output_delimiter: Option<u8> = config_file.output_delimiter.map(|d| parse_delimiter(&d)).transpose().map_err(Error::Config)?;
+        if let Some(fraction) = config_file.sample_fraction {
+            parse_fraction(&fraction.to_string()).map_err(|e| Error::Config(format!("sample-fraction in the config file is not valid. {}", e)))?;
+        }
+        if config_file.rate == Some(0) {
+            return Err(Error::Config("rate in the config file must be at least 1.".to_string()))
+        }
+        if config_file.timeout == Some(0) {
+            return Err(Error::Config("timeout in the config file must be at least 1.".to_string()))
+        }
+        if config_file.jobs == Some(0) {
+            return Err(Error::Config("jobs in the config file must be at least 1.".to_string()))
+        }
+
+        let input_format: Option<String> = layered(arg_matches, "input_format", file_input_format);
+        let is_json_input: bool = input_format.as_deref() == Some("json");
+        let is_url_input: bool = input_format.as_deref() == Some("urls");
+        let inputs: Vec<String> = 
+            match (arg_matches.get_many::<String>("input"), config_file.input) {
+                (Some(cli_inputs), _) => cli_inputs.cloned().collect(),
+                (None, Some(file_inputs)) => file_inputs,
+                (None, None) => Vec::new()
+            };
+        if inputs.is_empty() {
+            return Err(Error::Config("No input files were given. Pass them with -i, or list them under \"input\" in the config file.".to_string()))
+        }
+        let input_file_paths: Vec<std::path::PathBuf> = 
+            inputs
+                .iter()
+                .map(|input| {
+                    let mut input_file_path: std::path::PathBuf = std::fs::canonicalize(std::path::Path::new(input))
+                        .map_err(|source| Error::InputIo { path: std::path::PathBuf::from(input), source })?;
+                    // Verify that the input file matches the input format (tab-separated exports are also accepted)
+                    if input_file_path.as_path().extension().is_none() {
+                        input_file_path.set_extension(".csv");
+                    }
+                    else if is_json_input {
+                        if input_file_path.as_path().extension().is_some_and(|ext| ext != "json" && ext != "ndjson" && ext != "jsonl") {
+                            return Err(Error::Config(format!("Input file {} is not a json file.", input_file_path.display())))
+                        }
+                    }
+                    else if is_url_input {
+                        if input_file_path.as_path().extension().is_some_and(|ext| ext != "txt" && ext != "urls") {
+                            return Err(Error::Config(format!("Input file {} is not a text file of URLs.", input_file_path.display())))
+                        }
+                    }
+                    else if input_file_path.as_path().extension().is_some_and(|ext| ext != "csv" && ext != "tsv" && ext != "txt") {
+                        return Err(Error::Config(format!("Input file {} is not a csv.", input_file_path.display())))
+                    }
+                    Ok(input_file_path)
+                })
+                .collect::<Result<Vec<std::path::PathBuf>>>()?;
+        let is_json_output: bool = layered(arg_matches, "format", file_output_format).is_some_and(|format| format == "json");
+        let output_extension: &str = if is_json_output { "json" } else { "csv" };
+        let is_append_output: bool = layered(arg_matches, "write_mode", file_write_mode).is_some_and(|mode| mode == "append");
+        if is_append_output && is_json_output {
+            return Err(Error::Config("--write-mode append only works with csv output. A JSON array can't be added to.".to_string()))
+        }
+        // "-" writes the output to stdout instead of a file
+        let is_output_explicit: bool = arg_matches.value_source("output") == Some(ValueSource::CommandLine) || config_file.output.is_some();
+        let no_default_output: bool = layered(arg_matches, "no_default_output", config_file.no_default_output).unwrap_or(false);
+        let dry_run: bool = layered(arg_matches, "dry_run", config_file.dry_run).unwrap_or(false);
+        let output_arg: String = layered(arg_matches, "output", config_file.output).expect("output file is missing a default value.");
+        let output: OutputTarget = 
+            if dry_run || (no_default_output && !is_output_explicit) {
+                OutputTarget::Disabled
+            }
+            else if output_arg == "-" {
+                OutputTarget::Stdout
+            }
+            else {
+                let mut output_file_path: std::path::PathBuf = 
+                    std::path::absolute(
+                        std::path::Path::new(output_arg.as_str())
+                    ).map_err(|source| Error::OutputFile { path: std::path::PathBuf::from(output_arg), source })?;
+            
+                // Verify that the output file matches the output format. The default file name follows the format.
+                if !is_output_explicit || output_file_path.as_path().extension().is_none() {
+                    output_file_path.set_extension(output_extension);
+                }
+                else if output_file_path.as_path().extension().is_some_and(|ext| ext != output_extension) {
+                    return Err(Error::Config(format!("Output file is not a {}.", output_extension)))
+                } 
+                OutputTarget::File(output_file_path)
+            };
+
+        let output_fields: Vec<&'static str> = 
+            match arg_matches.get_many::<String>("fields") {
+                Some(cli_fields) => cli_fields.cloned().collect::<Vec<String>>(),
+                None => 
+                    config_file.fields
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|field| check_choice("fields", field, OUTPUT_FIELDS))
+                        .collect::<Result<Vec<String>>>()?
+            }
+            .iter()
+            .filter_map(|field| OUTPUT_FIELDS.iter().find(|known| *known == field).copied())
+            .collect();
+        let output_delimiter: u8 = layered(arg_matches, "output_delimiter", file_output_delimiter).expect("output_delimiter is missing a default value.");
+        let output_quote_style: csv::QuoteStyle = 
+            match layered(arg_matches, "output_quote_style", file_output_quote_style).expect("output_quote_style is missing a default value.").as_str() {
+                "always" => csv::QuoteStyle::Always,
+                "non-numeric" => csv::QuoteStyle::NonNumeric,
+                "never" => csv::QuoteStyle::Never,
+                _ => csv::QuoteStyle::Necessary
+            };
+
+        let system_types: Vec<String> = 
+            match arg_matches.get_many::<String>("system_type") {
+                Some(cli_system_types) => cli_system_types.cloned().collect(),
+                None => 
+                    config_file.system_type
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|t| check_choice("system-type", t, SOURCE_WATER_CATEGORIES))
+                        .collect::<Result<Vec<String>>>()?
+            };
+        // --proxy takes priority over the environment, the same as every other setting
+        let proxy: Option<String> = layered(arg_matches, "proxy", config_file.proxy).or_else(proxy_from_env);
+        if let Some(proxy) = proxy.as_deref() {
+            minreq::Proxy::new(proxy)
+                .map_err(|e| Error::Config(format!("Proxy \"{}\" is not a valid proxy URL. {}", crate::http::redact_proxy(proxy), e)))?;
+        }
+        let states: Vec<String> = 
+            match arg_matches.get_many::<String>("state") {
+                Some(cli_states) => cli_states.cloned().collect(),
+                None => 
+                    config_file.state
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|state| parse_state_code(state).map_err(Error::Config))
+                        .collect::<Result<Vec<String>>>()?
+            };
+        let file_date = |key: &str, date: Option<String>| -> Result<Option<chrono::NaiveDate>> {
+            date
+                .map(|date| parse_date(&date).map_err(|e| Error::Config(format!("{} in the config file is not valid. {}", key, e))))
+                .transpose()
+        };
+        let since: Option<chrono::NaiveDate> = layered(arg_matches, "since", file_date("since", config_file.since)?);
+        let until: Option<chrono::NaiveDate> = layered(arg_matches, "until", file_date("until", config_file.until)?);
+        if let (Some(since), Some(until)) = (since, until) {
+            if since > until {
+                return Err(Error::Config(format!("since ({}) is after until ({}), so every water system would be skipped.", since, until)))
+            }
+        }
+        let duplicate_columns: DuplicateColumns = 
+            match (layered(arg_matches, "pick_first", config_file.pick_first).unwrap_or(false), layered(arg_matches, "pick_last", config_file.pick_last).unwrap_or(false)) {
+                (true, true) => return Err(Error::Config("pick-first and pick-last can't both be set.".to_string())),
+                (true, false) => DuplicateColumns::PickFirst,
+                (false, true) => DuplicateColumns::PickLast,
+                (false, false) => DuplicateColumns::Error
+            };
+        let db_path: std::path::PathBuf = resolve_db_path(&layered(arg_matches, "db", config_file.db).expect("db is missing a default value."))?;
+        let delay: u32 = layered(arg_matches, "delay", config_file.delay).expect("delay is missing a default value.");
+        let min_delay: Option<u32> = layered(arg_matches, "min_delay", config_file.min_delay);
+        let max_delay: Option<u32> = layered(arg_matches, "max_delay", config_file.max_delay);
+        if min_delay.is_some_and(|min| min > delay) || max_delay.is_some_and(|max| max < delay) {
+            return Err(Error::Config(format!("The delay ({}) must be between min-delay ({}) and max-delay ({}).", 
+                delay, min_delay.map_or("none".to_string(), |min| min.to_string()), max_delay.map_or("none".to_string(), |max| max.to_string()))))
+        }
+
+        let sample: Option<SampleSize> = 
+            match (layered(arg_matches, "sample", config_file.sample), layered(arg_matches, "sample_fraction", config_file.sample_fraction)) {
+                (Some(_), Some(_)) => return Err(Error::Config("sample and sample-fraction can't both be set.".to_string())),
+                (Some(count), None) => Some(SampleSize::Count(count)),
+                (None, Some(fraction)) => Some(SampleSize::Fraction(fraction)),
+                (None, None) => None
+            };
+
+        Ok(Config {
+            input_file_paths,
+            is_json_input,
+            is_url_input,
+            input_delimiter: layered(arg_matches, "input_delimiter", file_input_delimiter).expect("input_delimiter is missing a default value."),
+            output,
+            output_delimiter,
+            is_json_output,
+            is_append_output,
+            output_quote_style,
+            output_bom: layered(arg_matches, "output_bom", config_file.output_bom).unwrap_or(false),
+            output_fields,
+            delay,
+            delay_jitter: layered(arg_matches, "delay_jitter", config_file.delay_jitter).expect("delay_jitter is missing a default value."),
+            min_delay,
+            max_delay,
+            is_header: layered(arg_matches, "header_is", config_file.header_is).expect("header_is is missing a default value."),
+            st_header: layered(arg_matches, "header_state", config_file.header_state).expect("header_state is missing a default value."),
+            ws_header: layered(arg_matches, "header_ws", config_file.header_ws).expect("header_ws is missing a default value."),
+            max_requests: layered(arg_matches, "max_requests", config_file.max_requests),
+            rate: layered(arg_matches, "rate", config_file.rate),
+            print_parsed: layered(arg_matches, "print_parsed", config_file.print_parsed).unwrap_or(false),
+            no_cache: layered(arg_matches, "no_cache", config_file.no_cache).unwrap_or(false),
+            active_only: layered(arg_matches, "active_only", config_file.active_only).unwrap_or(false),
+            population_min: layered(arg_matches, "population_min", config_file.population_min),
+            since,
+            until,
+            progress_json: layered(arg_matches, "progress_json", config_file.progress_json).unwrap_or(false),
+            duplicate_columns,
+            log_level: log_level(
+                layered(arg_matches, "verbose", config_file.verbose).unwrap_or(0), 
+                layered(arg_matches, "quiet", config_file.quiet).unwrap_or(false)
+            ),
+            max_redirects: layered(arg_matches, "max_redirects", config_file.max_redirects).expect("max_redirects is missing a default value."),
+            user_agent: layered(arg_matches, "user_agent", config_file.user_agent).expect("user_agent is missing a default value."),
+            proxy,
+            system_types,
+            skip_empty_buyers: layered(arg_matches, "skip_empty_buyers", config_file.skip_empty_buyers).unwrap_or(false),
+            direction: 
+                match layered(arg_matches, "direction", file_direction).as_deref() {
+                    Some("buyers") => Direction::Buyers,
+                    Some("sellers") => Direction::Sellers,
+                    _ => Direction::Both
+                },
+            input_encoding: layered(arg_matches, "input_encoding", file_input_encoding).expect("input_encoding is missing a default value."),
+            states,
+            limit: layered(arg_matches, "limit", config_file.limit),
+            sample,
+            sample_seed: layered(arg_matches, "sample_seed", config_file.sample_seed),
+            store_html: layered(arg_matches, "store_html", config_file.store_html).unwrap_or(false),
+            cache_dir: layered(arg_matches, "cache_dir", config_file.cache_dir).map(std::path::PathBuf::from),
+            manifest: layered(arg_matches, "manifest", config_file.manifest).map(std::path::PathBuf::from),
+            fail_fast: layered(arg_matches, "fail_fast", config_file.fail_fast).unwrap_or(false),
+            only_new: layered(arg_matches, "only_new", config_file.only_new).unwrap_or(false),
+            db_path,
+            fast_db: layered(arg_matches, "fast_db", config_file.fast_db).unwrap_or(false),
+            upsert: layered(arg_matches, "upsert", config_file.upsert).unwrap_or(false),
+            retries: layered(arg_matches, "retries", config_file.retries).expect("retries is missing a default value."),
+            timeout: layered(arg_matches, "timeout", config_file.timeout).expect("timeout is missing a default value."),
+            resume: layered(arg_matches, "resume", config_file.resume).unwrap_or(false),
+            dry_run,
+            jobs: layered(arg_matches, "jobs", config_file.jobs).expect("jobs is missing a default value."),
+            max_depth: layered(arg_matches, "max_depth", config_file.max_depth).expect("max_depth is missing a default value.")
+        })
+    }
+}
+
+static INPUT_FORMATS: &[&str] = &["csv", "json", "urls"];
+static DEFAULT_USER_AGENT: &str = concat!("tceq-scraper/", env!("CARGO_PKG_VERSION"));
+static OUTPUT_FORMATS: &[&str] = &["csv", "json"];
+static DIRECTIONS: &[&str] = &["buyers", "sellers", "both"];
+static WRITE_MODES: &[&str] = &["overwrite", "append"];
+static EXPORT_TABLES: &[&str] = &["details", "relationships"];
+static OUTPUT_QUOTE_STYLES: &[&str] = &["necessary", "always", "non-numeric", "never"];
+
+// Picks the value given on the command line, then the value from the config file, then the argument's default value
+fn layered<T: Clone + Send + Sync + 'static>(arg_matches: &ArgMatches, id: &str, file_value: Option<T>) -> Option<T> {
+    if arg_matches.value_source(id) == Some(ValueSource::CommandLine) {
+        return arg_matches.get_one::<T>(id).cloned()
+    }
+    file_value.or_else(|| arg_matches.get_one::<T>(id).cloned())
+}
+
+// -q only shows warnings and errors. Each -v shows more detail, down to every database write and wait.
+fn log_level(verbose: u8, quiet: bool) -> log::LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Warn,
+        (false, 0) => log::LevelFilter::Info,
+        (false, 1) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace
+    }
+}
+
+// The database doesn't need to exist yet. It's created, along with its tables, when it's opened.
+fn resolve_db_path(db_arg: &str) -> Result<std::path::PathBuf> {
+    let db_path: std::path::PathBuf = 
+        std::path::absolute(std::path::Path::new(db_arg))
+            .map_err(|e| Error::Config(format!("Unable to resolve database path {}. {}", db_arg, e)))?;
+    if db_path.as_path().extension().is_none_or(|ext| ext != "db3" && ext != "db" && ext != "sqlite" && ext != "sqlite3") {
+        return Err(Error::Config(format!("Database file {} is not a SQLite database (.db3, .db, .sqlite, or .sqlite3).", db_path.display())))
+    }
+    Ok(db_path)
+}
+
+// Rejects config file values that the command line wouldn't accept either
+fn check_choice(key: &str, value: String, choices: &[&str]) -> Result<String> {
+    if choices.contains(&value.as_str()) {
+        return Ok(value)
+    }
+    Err(Error::Config(format!("{} in the config file must be one of {}, got \"{}\".", key, choices.join(", "), value)))
+}
+
+fn command() -> Command {
+    // Make the default output file name: [datetime]_out.csv. It's resolved against the current directory
+    // along with any other output path, so a missing or unreadable working directory is reported as an error there.
+    let since_epoch: u64 =
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+    let default_output_path: String = since_epoch.to_string() + "_out.csv";
+
+    // Handle arguments
+    Command::new("tceq-scraper")
+        .version("0.1")
+        .about("Compiles water system data from https://dww2.tceq.texas.gov/ into a csv file.")
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            Command::new("reparse")
+                .about("Runs the parser again over stored pages and updates the database, without sending any requests.")
+                .long_about("Runs the parser again over pages stored with --store-html (or saved to a directory) and replaces the water details and relationships stored from them, without sending any requests. Use this to apply a parser fix to pages that were already scraped. Reports how many relationships were added, removed, or changed compared to the previous parse.")
+                .arg(
+                    arg!(--"from-html" <DIR>)
+                        .value_parser(value_parser!(String))
+                        .id("from_html")
+                        .required(false)
+                        .help("Read pages from a directory instead of the database.")
+                        .long_help("Read pages from the .html and .htm files in a directory instead of the page_html table of the database. Each file must be named after the water system number of its page (i.e., TX2270192.html).")
+                        .action(ArgAction::Set)
+                )
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Writes a table of the database to a csv or JSON file, without sending any requests.")
+                .long_about("Writes everything stored in the database so far to a csv or JSON file, without scraping. Exports either the water details (one row per water system, including systems only known as a buyer or seller) or the relationships (one row per buyer and seller pair, with both names).")
+                .arg(
+                    arg!(--table <TABLE>)
+                        .value_parser(EXPORT_TABLES.to_vec())
+                        .id("table")
+                        .required(false)
+                        .help("Which table to export.")
+                        .long_help("\"details\" exports the water_systems table. \"relationships\" exports the water_buyer_relationships table, with the names of each buyer and seller.")
+                        .action(ArgAction::Set)
+                        .default_value("relationships")
+                )
+                .arg(
+                    arg!(-o --output <PATH>)
+                        .value_parser(value_parser!(String))
+                        .id("output")
+                        .required(false)
+                        .help("File to write the export to, or \"-\" for stdout.")
+                        .long_help("File to write the export to. An existing file is replaced. By default, or with \"-\", the export is written to stdout.")
+                        .action(ArgAction::Set)
+                        .default_value("-")
+                )
+                .arg(
+                    arg!(--format <FORMAT>)
+                        .value_parser(OUTPUT_FORMATS.to_vec())
+                        .id("format")
+                        .required(false)
+                        .help("Format of the export.")
+                        .long_help("\"csv\" writes one row per water system or relationship. \"json\" writes a JSON array with one object per water system or relationship, using the same field names as the csv header.")
+                        .action(ArgAction::Set)
+                        .default_value("csv")
+                )
+                .arg(
+                    arg!(--"output-delimiter" <CHAR>)
+                        .value_parser(parse_delimiter)
+                        .id("output_delimiter")
+                        .required(false)
+                        .help("Character used to separate columns in a csv export.")
+                        .long_help("Single character used to separate columns in a csv export. Use \"tab\" or \"\\t\" for tab-separated output, or \";\" for spreadsheets that expect semicolons.")
+                        .action(ArgAction::Set)
+                        .default_value(",")
+                )
+        )
+        .arg(
+            arg!(-i <INPUT_CSV>)
+                .value_parser(value_parser!(String))
+                .id("input")
+                .long("input")
+                .required(false)
+                .num_args(1..)
+                .help("Provide a path to one or more csv files that contain TCEQ water detail info.")
+                .long_help("Required unless the config file lists input files. CSV should consist of three columns:\n\ttinwsys_is_number\n\ttinwsys_st_code\n\twsnumber\nAll of these values can be found in the URL of the water detail page. (Example: https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=5969&tinwsys_st_code=TX&wsnumber=TX2270001%20%20%20&DWWState=TX)\nMultiple files can be given (i.e., -i first.csv second.csv) and are scraped in order. Each file's headers are mapped separately using the same header arguments, and a water system listed in more than one file is only scraped once.")
+                .action(ArgAction::Append)
+        )
+        .arg(
+            arg!(--"input-format" <FORMAT>)
+                .value_parser(INPUT_FORMATS.to_vec())
+                .id("input_format")
+                .required(false)
+                .help("Format of the input files.")
+                .long_help("\"csv\" reads delimited files using the header arguments to find each column. \"json\" reads either a JSON array or newline-delimited JSON objects shaped like {\"is_number\": \"5969\", \"st_code\": \"TX\", \"ws_number\": \"TX2270192\"}. \"urls\" reads one water system detail page URL per line (i.e., https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=5969&tinwsys_st_code=TX&wsnumber=TX2270001%20%20%20), taking each system from the tinwsys_is_number, tinwsys_st_code, and wsnumber query parameters. The header and delimiter arguments are ignored for JSON and URL input.")
+                .action(ArgAction::Set)
+                .default_value("csv")
+        )
+        .arg(
+            arg!(--"input-delimiter" <CHAR>)
+                .value_parser(parse_input_delimiter)
+                .id("input_delimiter")
+                .required(false)
+                .help("Character used to separate columns in the input files, or \"auto\" to detect it.")
+                .long_help("Single character used to separate columns in the input files. Use \"tab\" or \"\\t\" for tab-separated files. With \"auto\", the delimiter is detected from each file's header row by picking whichever of comma, tab, semicolon, or pipe appears the most.")
+                .action(ArgAction::Set)
+                .default_value(",")
+        )
+        .arg(
+            arg!(-o <OUTPUT_CSV>)
+                .value_parser(value_parser!(String))
+                .id("output")
+                .long("output")
+                .required(false)
+                .help("Choose a path to store water data, or \"-\" to write it to stdout.")
+                .long_help("Choose a path to store water data. Use \"-\" to write the output to stdout instead, in which case all progress messages are written to stderr. When left out, a file named [seconds since epoch]_out.csv (or _out.json with --format json) is created in the current directory, unless --no-default-output is given.")
+                .action(ArgAction::Set)
+                .default_value(default_output_path)
+        )
+        .arg(
+            arg!(-d <DELAY>)
+                .value_parser(value_parser!(u32))
+                .id("delay")
+                .long("delay")
+                .required(false)
+                .help("Delay (milliseconds) between website requests.")
+                .long_help("To avoid getting IP blocked for large requests, add a delay between each request to the website. The delay is counted from the start of one request to the start of the next, across every --jobs worker.")
+                .action(ArgAction::Set)
+                .default_value("3000")
+        )
+        .arg(
+            arg!(--"delay-jitter" <MS>)
+                .value_parser(value_parser!(u32))
+                .id("delay_jitter")
+                .required(false)
+                .help("Add a random 0 to this many milliseconds to each delay between website requests.")
+                .long_help("Spaces requests by --delay plus a random number of milliseconds between 0 and this value, picked again for every request, so that the requests don't arrive at a fixed interval. The wait is still kept within --min-delay and --max-delay. A jitter of 0 keeps the fixed delay, for runs that need to be reproducible.")
+                .action(ArgAction::Set)
+                .default_value("0")
+        )
+        .arg(
+            arg!(--"min-delay" <MILLISECONDS>)
+                .value_parser(value_parser!(u32))
+                .id("min_delay")
+                .required(false)
+                .help("Never wait less than this many milliseconds between website requests.")
+                .long_help("Hard lower bound (milliseconds) on the wait between website requests, no matter how the wait is varied from --delay. Must not be more than --delay. Useful for keeping to a pacing agreement with the site operator.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"max-delay" <MILLISECONDS>)
+                .value_parser(value_parser!(u32))
+                .id("max_delay")
+                .required(false)
+                .help("Never wait more than this many milliseconds between website requests.")
+                .long_help("Hard upper bound (milliseconds) on the wait between website requests, no matter how the wait is varied from --delay. Must not be less than --delay.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(-w <WS_NUMBER_HEADER>)
+                .value_parser(value_parser!(String))
+                .id("header_ws")
+                .long("header_ws")
+                .required(false)
+                .help("Map the \"ws number\" header from the input file.")
+                .long_help("In case the input file's \"ws number\" header does not go by the default name (\"ws_number\"), use this parameter to set a column from the input file as the \"ws number\" column using its header name.") 
+                .action(ArgAction::Set)
+                .default_value("ws_number")
+        )
+        .arg(
+            arg!(-n <IS_NUMBER_HEADER>)
+                .value_parser(value_parser!(String))
+                .id("header_is")
+                .long("header_is")
+                .required(false)
+                .help("Map the \"is number\" header from the input file.")
+                .long_help("In case the input file's \"is number\" header does not go by the default name (\"is_number\"), use this parameter to set a column from the input file as the \"is number\" column using its header name.") 
+                .action(ArgAction::Set)
+                .default_value("is_number")
+        )
+        .arg(
+            arg!(-s <STATE_CODE_HEADER>)
+                .value_parser(value_parser!(String))
+                .id("header_state")
+                .long("header_state")
+                .required(false)
+                .help("Map the \"state code\" header from the input file.")
+                .long_help("In case the input file's \"state code\" header does not go by the default name (\"st_code\"), use this parameter to set a column from the input file as the \"state code\" column using its header name.")
+                .action(ArgAction::Set)
+                .default_value("st_code")
+        )
+        .arg(
+            arg!(--"max-requests" <MAX_REQUESTS>)
+                .value_parser(value_parser!(u32))
+                .id("max_requests")
+                .required(false)
+                .help("Stop sending requests once this many have been made.")
+                .long_help("Hard cap on the total number of requests sent to the website during this run. Once the cap is reached, no new requests are sent, everything scraped so far is saved, and the scraper exits. By default there is no cap.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--format <FORMAT>)
+                .value_parser(OUTPUT_FORMATS.to_vec())
+                .id("format")
+                .required(false)
+                .help("Format of the output file.")
+                .long_help("\"csv\" writes one row per buyer, repeating the water system's details on each row. \"json\" writes a JSON array with one object per water system, holding its details and a \"buyers\" list of its relationships. The output file must end in .json when \"json\" is chosen. The delimiter, quote style, and BOM arguments only apply to csv output.")
+                .action(ArgAction::Set)
+                .default_value("csv")
+        )
+        .arg(
+            arg!(--"write-mode" <MODE>)
+                .value_parser(WRITE_MODES.to_vec())
+                .id("write_mode")
+                .required(false)
+                .help("Whether to replace an existing output file or add to it.")
+                .long_help("\"overwrite\" replaces the output file if it already exists. \"append\" adds the new rows to the end of it instead, and only writes the header row if the file is new or empty. A warning is shown if the existing file doesn't start with the same header row. Only csv output can be appended to.")
+                .action(ArgAction::Set)
+                .default_value("overwrite")
+        )
+        .arg(
+            arg!(--"output-delimiter" <CHAR>)
+                .value_parser(parse_delimiter)
+                .id("output_delimiter")
+                .required(false)
+                .help("Character used to separate columns in the output file.")
+                .long_help("Single character used to separate columns in the output file. Use \"tab\" or \"\\t\" for tab-separated output.")
+                .action(ArgAction::Set)
+                .default_value(",")
+        )
+        .arg(
+            arg!(--fields <FIELDS>)
+                .value_parser(OUTPUT_FIELDS.to_vec())
+                .id("fields")
+                .required(false)
+                .value_delimiter(',')
+                .help("Only write these columns to the output file, in this order.")
+                .long_help("Comma-separated list of the output columns to write, in the order given (i.e., --fields ws_number,name,buyer,population). For JSON output, the water system fields are picked the same way, and each buyer and seller keeps the fields matching the selected relationship columns (\"seller\" also keeps seller_name, and \"population_raw\" selects the population text). The database always gets everything. By default, every column is written.")
+                .action(ArgAction::Append)
+        )
+        .arg(
+            arg!(--"output-quote-style" <QUOTE_STYLE>)
+                .value_parser(OUTPUT_QUOTE_STYLES.to_vec())
+                .id("output_quote_style")
+                .required(false)
+                .help("When to wrap output fields in quotes.")
+                .long_help("\"necessary\" only quotes fields that contain the delimiter, quotes, or line breaks. \"always\" quotes every field. \"non-numeric\" quotes every field that isn't a number. \"never\" never quotes fields, even if that produces an invalid csv.")
+                .action(ArgAction::Set)
+                .default_value("necessary")
+        )
+        .arg(
+            arg!(--"output-bom")
+                .id("output_bom")
+                .required(false)
+                .help("Start the output file with a UTF-8 byte order mark.")
+                .long_help("Writes a UTF-8 byte order mark (BOM) at the start of the output file so that Excel on Windows reads accented water system names correctly. Off by default since most other programs don't expect a BOM.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--rate <REQUESTS_PER_MINUTE>)
+                .value_parser(value_parser!(u32).range(1..))
+                .id("rate")
+                .required(false)
+                .help("Maximum number of requests sent to the website per minute.")
+                .long_help("Caps the overall request rate, regardless of how the delay is configured. Requests are spread evenly across each minute. By default there is no cap beyond the delay.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"print-parsed")
+                .id("print_parsed")
+                .required(false)
+                .help("Print each scraped water detail and its buyers.")
+                .long_help("After each page is parsed, print the water detail and every buyer relationship found on it. Useful for checking what the parser extracted from a few pages.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"no-cache")
+                .id("no_cache")
+                .required(false)
+                .help("Always download and parse every page, even if it hasn't changed.")
+                .long_help("By default, the ETag and Last-Modified values that the website sends back for each page are stored in the database, and the next run asks the website whether the page has changed since. Unchanged pages are skipped without being parsed. Use this flag to parse every page regardless. With --cache-dir, this also fetches every page again instead of loading the saved copy.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"active-only")
+                .id("active_only")
+                .required(false)
+                .help("Skip scraping buyers of inactive water systems.")
+                .long_help("Inactive water systems are still recorded along with their activity status, but their \"Buyers of Water\" table is not scraped. By default, buyers are scraped for every water system regardless of activity status.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--db <DB_PATH>)
+                .value_parser(value_parser!(String))
+                .id("db")
+                .required(false)
+                .global(true)
+                .help("SQLite database to store scraped data in.")
+                .long_help("Path to the SQLite database that scraped water details and relationships are stored in. Point separate runs at separate copies of the database to keep their datasets apart. If the file doesn't exist, a new database is created with the scraper's tables. A database created by an older version of the scraper gets any tables and columns it's missing when it's opened. --dry-run only reads the database, so it refuses such a database until a normal run has updated it.")
+                .action(ArgAction::Set)
+                .default_value(crate::db::DEFAULT_DATABASE_PATH)
+        )
+        .arg(
+            arg!(--upsert)
+                .id("upsert")
+                .required(false)
+                .help("Update water systems that are already in the database instead of replacing them.")
+                .long_help("By default, storing a page replaces the row of its water system in the database, so details missing from the new copy of the page are lost. (Systems only listed as another system's buyer or seller never replace a stored row.) With --upsert, the stored row is updated in place: the name and state code are refreshed, the other details are only overwritten when the new copy has them, and the created date and buyer and seller counts are kept.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"fast-db")
+                .id("fast_db")
+                .required(false)
+                .help("Speed up database writes at the cost of durability.")
+                .long_help("Switches the database to write-ahead logging (WAL) and only syncs it to disk at checkpoints (synchronous=NORMAL). Large runs spend much less time waiting on the disk. A crash or power loss can lose the last pages stored before it, but won't corrupt the database. WAL mode is kept by the database file after the run, and SQLite keeps -wal and -shm files next to it while it's open.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--config <PATH>)
+                .value_parser(value_parser!(String))
+                .id("config")
+                .required(false)
+                .help("Load default argument values from a TOML file.")
+                .long_help("TOML file whose keys are the long argument names without the leading dashes (i.e., delay = 5000, header_ws = \"wsnumber\", input = [\"first.csv\", \"second.csv\"], active-only = true). Arguments given on the command line override the values in the file. Paths in the file are relative to the directory the scraper is run from.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"population-min" <POPULATION>)
+                .value_parser(value_parser!(u64))
+                .id("population_min")
+                .required(false)
+                .help("Skip storing relationships whose population is below this number.")
+                .long_help("Relationships from the 'Buyers of Water' table are only stored when their population is at least this number. Commas and whitespace are ignored when reading the population (i.e., \"1,234\" is 1234). Relationships with a blank or non-numeric population are always stored, since there is no number to compare. By default, every relationship is stored.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--since <DATE>)
+                .value_parser(parse_date)
+                .id("since")
+                .required(false)
+                .help("Skip water systems last updated before this date (YYYY-MM-DD).")
+                .long_help("Water systems whose page says they were last updated before this date are neither stored nor written to the output (i.e., --since 2024-01-31). The date is written as YYYY-MM-DD and is inclusive. Pages still have to be fetched to read their date, and systems whose page doesn't give one are always kept. By default, systems are kept no matter when they were updated.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--until <DATE>)
+                .value_parser(parse_date)
+                .id("until")
+                .required(false)
+                .help("Skip water systems last updated after this date (YYYY-MM-DD).")
+                .long_help("Water systems whose page says they were last updated after this date are neither stored nor written to the output (i.e., --until 2024-12-31). The date is written as YYYY-MM-DD and is inclusive. Systems whose page doesn't give a date are always kept. By default, systems are kept no matter when they were updated.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"progress-json")
+                .id("progress_json")
+                .required(false)
+                .help("Write one JSON line per finished row to stderr.")
+                .long_help("After each row is done, writes a line like {\"row\":1,\"ws_number\":\"TX2270192\",\"status\":\"scraped\",\"elapsed_ms\":412} to stderr so that another program can track progress. status is one of \"scraped\", \"filtered\" (skipped by a filter such as --since or --system-type, or a page without a name), \"not_modified\", \"status_error\", \"request_error\", \"database_error\" (the page couldn't be stored, so nothing from it was saved), or \"already_scraped\" (skipped by --resume without sending a request). The usual progress messages are still printed.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"pick-first")
+                .id("pick_first")
+                .required(false)
+                .conflicts_with("pick_last")
+                .help("Use the first of any input columns that share a mapped header name.")
+                .long_help("If an input file has more than one column with the same mapped header name (i.e., two \"ws_number\" columns), use the first one and print a warning. By default, such files are rejected.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"pick-last")
+                .id("pick_last")
+                .required(false)
+                .help("Use the last of any input columns that share a mapped header name.")
+                .long_help("If an input file has more than one column with the same mapped header name (i.e., two \"ws_number\" columns), use the last one and print a warning. By default, such files are rejected.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(-v --verbose)
+                .id("verbose")
+                .required(false)
+                .global(true)
+                .conflicts_with("quiet")
+                .help("Print more detail. Repeat for even more (-vv).")
+                .long_help("Print more detail about the run, such as which input columns are ignored because they aren't mapped by the -w, -n, or -s arguments, which rows --sample picked, and each database write and wait. Repeat for even more detail (-vv). In the config file, use verbose = 1 or verbose = 2. All messages go to stderr, and the RUST_LOG environment variable overrides this setting.")
+                .action(ArgAction::Count)
+        )
+        .arg(
+            arg!(-q --quiet)
+                .id("quiet")
+                .required(false)
+                .global(true)
+                .help("Only print warnings, errors, and the breakdown of responses.")
+                .long_help("Only print warnings and errors, i.e. skipped rows and failed requests. The run summary and per-row progress are left out, except for the breakdown of responses by status code, which is always printed at the end of the run.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"max-redirects" <MAX_REDIRECTS>)
+                .value_parser(value_parser!(usize))
+                .id("max_redirects")
+                .required(false)
+                .help("Maximum number of redirects followed for each page. 0 disables following redirects.")
+                .long_help("If the website redirects a water detail page, the request is re-sent to the new location up to this many times. Redirect loops are detected and treated as a failed request, as is going past the cap. With 0, any redirect is treated as a failed request.")
+                .action(ArgAction::Set)
+                .default_value("5")
+        )
+        .arg(
+            arg!(--"user-agent" <USER_AGENT>)
+                .value_parser(value_parser!(String))
+                .id("user_agent")
+                .required(false)
+                .help("User-Agent header sent with every request.")
+                .long_help("Identifies the scraper to the website. The default names the scraper and its version. Add contact details (i.e., \"tceq-scraper/0.1.0 (jane@example.com)\") so the site operator can reach you instead of blocking your IP.")
+                .action(ArgAction::Set)
+                .default_value(DEFAULT_USER_AGENT)
+        )
+        .arg(
+            arg!(--proxy <URL>)
+                .value_parser(value_parser!(String))
+                .id("proxy")
+                .required(false)
+                .help("HTTP proxy to send every request through.")
+                .long_help("Sends every request through this proxy, given as [http://][user[:password]@]host[:port] (i.e., http://proxy.example.com:8080). The HTTPS_PROXY and HTTP_PROXY environment variables are used when this isn't given. Without any of them, the scraper connects to the website directly.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"system-type" <TYPE>)
+                .value_parser(SOURCE_WATER_CATEGORIES.to_vec())
+                .id("system_type")
+                .required(false)
+                .help("Only store water systems whose primary source water type is in this category.")
+                .long_help("Only store water systems whose primary source water type falls in one of the given categories. Can be given more than once (i.e., --system-type surface --system-type purchased). Purchased water counts as \"purchased\" no matter where it comes from. Systems that don't match, including ones without a source water type, are skipped along with their buyers. By default, every type is stored.")
+                .action(ArgAction::Append)
+        )
+        .arg(
+            arg!(--direction <DIRECTION>)
+                .value_parser(DIRECTIONS.to_vec())
+                .id("direction")
+                .required(false)
+                .help("Which relationships to record: buyers, sellers, or both.")
+                .long_help("\"buyers\" only reads the 'Buyers of Water' table of each page, for mapping where a system's water goes. \"sellers\" only reads the 'Sellers of Water' table, for mapping where it comes from. The other table is skipped entirely: nothing from it is stored or written to the output. With \"sellers\", buyer counts are left unknown and systems without buyers are still recorded, since their buyers aren't looked at. --max-depth follows buyers, so it finds nothing new with \"sellers\".")
+                .default_value("both")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"skip-empty-buyers")
+                .id("skip_empty_buyers")
+                .required(false)
+                .help("Don't record water systems that have no buyers.")
+                .long_help("By default, every scraped water system is recorded, with a buyer_count of 0 when its page said it has no buyers, or an empty buyer_count when the 'Buyers of Water' table was missing and its buyers are unknown. With this flag, a water system whose page lists no buyers (or has no 'Buyers of Water' table) is skipped instead. Skipped systems aren't in the database, so --resume and --only-new fetch them again on the next run.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"no-default-output")
+                .id("no_default_output")
+                .required(false)
+                .conflicts_with("output")
+                .help("Don't create the timestamped output csv when -o isn't given.")
+                .long_help("Without -o, a timestamped output csv is created in the current directory on every run. With this flag, no csv is created and scraped data is only stored in the database. Giving -o (on the command line or in the config file) always writes the csv.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"input-encoding" <ENCODING>)
+                .value_parser(parse_encoding)
+                .id("input_encoding")
+                .required(false)
+                .help("Text encoding of the input files.")
+                .long_help("Text encoding of the input files, given as a WHATWG encoding label (i.e., \"utf-8\", \"windows-1252\", \"latin1\"). Use \"windows-1252\" for csv files saved by Excel on Windows whose water system names have accents. Files that start with a byte order mark are always read using the encoding the mark indicates.")
+                .action(ArgAction::Set)
+                .default_value("utf-8")
+        )
+        .arg(
+            arg!(--state <CODE>)
+                .value_parser(parse_state_code)
+                .id("state")
+                .required(false)
+                .value_delimiter(',')
+                .help("Only scrape input rows from these states.")
+                .long_help("Only scrape input rows whose state code (the tinwsys_st_code column) is one of these two-letter codes. Can be given more than once or as a comma-separated list (i.e., --state TX,OK). Case doesn't matter. Other rows are skipped before --limit and --sample are applied. By default, rows from every state are scraped.")
+                .action(ArgAction::Append)
+        )
+        .arg(
+            arg!(--limit <ROWS>)
+                .value_parser(value_parser!(usize))
+                .id("limit")
+                .required(false)
+                .help("Only scrape the first this many input rows.")
+                .long_help("Only scrape the first this many input rows, counted after blank and duplicate rows are removed, and leave the rest of the input alone. Combine with --dry-run to quickly check the header mapping and parsing of a large input. With --sample, the sample is picked from these rows.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--sample <COUNT>)
+                .value_parser(value_parser!(usize))
+                .id("sample")
+                .required(false)
+                .conflicts_with("sample_fraction")
+                .help("Scrape a random subset of this many input rows.")
+                .long_help("Scrape a random subset of this many input rows instead of the whole input, i.e. to spot-check a large input or estimate how long a full run takes. Sampled rows are scraped in input order, after blank and duplicate rows are removed. The seed is printed so the same rows can be picked again with --sample-seed. Use -v to list the sampled rows.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"sample-fraction" <FRACTION>)
+                .value_parser(parse_fraction)
+                .id("sample_fraction")
+                .required(false)
+                .help("Scrape a random subset of this fraction of the input rows (i.e., 0.1).")
+                .long_help("Same as --sample, but the subset size is given as a fraction of the input rows between 0 and 1 (i.e., 0.1 scrapes one in ten rows). The number of rows is rounded up.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"sample-seed" <SEED>)
+                .value_parser(value_parser!(u64))
+                .id("sample_seed")
+                .required(false)
+                .help("Seed for picking --sample rows.")
+                .long_help("Seed for the random number generator that picks --sample and --sample-fraction rows. The same seed and input always pick the same rows. By default a random seed is used.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"store-html")
+                .id("store_html")
+                .required(false)
+                .help("Save the raw HTML of each scraped page to the database.")
+                .long_help("Save the raw HTML of each scraped page into the page_html table of the database, keyed by water system number. This keeps a copy of exactly what the parser saw, so the data can be checked or parsed again after a parser fix. Only the latest copy of each page is kept. Off by default, since pages add up quickly.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--manifest <PATH>)
+                .value_parser(value_parser!(String))
+                .id("manifest")
+                .required(false)
+                .help("Write a csv listing every row processed, its URL, and what came back.")
+                .long_help("Writes a csv with the columns source_row, ws_number, url, status_code, and outcome for every row the scraper processed, separate from the scraped data. outcome is \"scraped\", \"skipped\" (i.e., filtered out, unchanged, or past --max-requests), or \"failed\". status_code is blank when no response came back. Each line is written as soon as its row is done, so a run that stops early still leaves a partial manifest. An existing file is replaced.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"cache-dir" <PATH>)
+                .value_parser(value_parser!(String))
+                .id("cache_dir")
+                .required(false)
+                .help("Save fetched pages to this directory and load them from it on later runs.")
+                .long_help("Saves each fetched page to this directory as <is_number>_<ws_number>.html. On later runs, pages found there are parsed without sending a request, which saves hammering the website while working on the parser. Pass --no-cache to fetch every page again and replace the saved copies. The directory is created if it doesn't exist.")
+                .action(ArgAction::Set)
+        )
+        .arg(
+            arg!(--"fail-fast")
+                .id("fail_fast")
+                .required(false)
+                .help("Stop the run at the first failed request or unparsable page.")
+                .long_help("Stop the run at the first request that fails, response that isn't OK, or page that is missing what the parser looks for, and print the input row that caused it. Everything scraped before that row is still saved. By default, failed rows are logged and the run continues.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--"only-new")
+                .id("only_new")
+                .required(false)
+                .help("Only scrape water systems that aren't in the database yet.")
+                .long_help("Skip every input row whose water system has already been scraped into the database, and report how many were new. Use this to add newly published systems from a fresh input list to an existing database without scraping everything again. Systems that are only in the database as the buyer of another system have never had their own page scraped, so they are still scraped.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(--retries <N>)
+                .value_parser(value_parser!(u32))
+                .id("retries")
+                .required(false)
+                .help("Retry a failed request up to this many times.")
+                .long_help("Retry requests that fail without a response, or that get a server error (5xx) or a 429 Too Many Requests response, up to this many times. The wait before each retry doubles, starting at --delay (i.e., 3000, 6000, then 12000 milliseconds). A 429 is first retried up to 5 more times on its own, after pausing every request for as long as its Retry-After header asks (60 seconds if it doesn't say), and only then counts toward these retries. Other responses, like a 404, are not retried. Retries count toward --max-requests. Use 0 to never retry.")
+                .action(ArgAction::Set)
+                .default_value("3")
+        )
+        .arg(
+            arg!(--timeout <SECONDS>)
+                .value_parser(value_parser!(u64).range(1..))
+                .id("timeout")
+                .required(false)
+                .help("Give up on a request after this many seconds.")
+                .long_help("Give up on a request if the website hasn't answered after this many seconds, so that a hung connection can't stall the run. A timed out request is retried like any other failed request (see --retries), and is reported with its row number and url if it still fails.")
+                .action(ArgAction::Set)
+                .default_value("30")
+        )
+        .arg(
+            arg!(--resume)
+                .id("resume")
+                .required(false)
+                .help("Pick up an interrupted run where it left off.")
+                .long_help("Before scraping each row, check the database and skip the row if its water system and its 'Buyers of Water' table were already stored. Re-run an interrupted scrape with the same input and this flag to continue from where it stopped instead of starting over at row 1. Skipped rows don't send a request or count toward --max-requests.")
+                .action(ArgAction::SetTrue)
+        )
+        .arg(
+            arg!(-j --jobs <N>)
+                .value_parser(value_parser!(u32).range(1..))
+                .id("jobs")
+                .required(false)
+                .help("Number of pages to fetch at once.")
+                .long_help("Fetches this many pages at once on separate threads, which helps when the website is slower to answer than --delay. Every worker shares --delay and --rate, so the overall request rate stays the same no matter how many workers there are. Pages are still stored and written to the output in input order, and only one thread writes to the database.")
+                .action(ArgAction::Set)
+                .default_value("1")
+        )
+        .arg(
+            arg!(--"max-depth" <N>)
+                .value_parser(value_parser!(u32))
+                .id("max_depth")
+                .required(false)
+                .help("Also scrape the buyers of each scraped system, up to this many hops from the input rows.")
+                .long_help("After the input rows are scraped, scrapes the buyers found on their pages, then the buyers found on those pages, and so on, up to this many hops from the input rows. Buyers are only listed by water system number, so their IS number is looked up on the website's search page first (one extra request each, unless the database already has it). Each water system is scraped at most once per run. By default (0), only the input rows are scraped.")
+                .action(ArgAction::Set)
+                .default_value("0")
+        )
+        .arg(
+            arg!(--"dry-run")
+                .id("dry_run")
+                .required(false)
+                .help("Scrape and parse every page, but don't write to the database or the output file.")
+                .long_help("Sends every request and parses every page as usual, then prints how many water details and relationships would have been stored from each page instead of storing them. No output file is created, and the database is opened read-only, so this is safe to run against a new page layout. Cached page validators are not sent, so every page is downloaded and parsed.")
+                .action(ArgAction::SetTrue)
+        )
+}
+
+// Parses a single-byte csv delimiter. Accepts "tab" and "\t" as aliases for a tab character.
+fn parse_delimiter(delimiter: &str) -> std::result::Result<u8, String> {
+    match delimiter {
+        "tab" | "\\t" => Ok(b'\t'),
+        d if d.len() == 1 && d.is_ascii() => Ok(d.as_bytes()[0]),
+        d => Err(format!("Delimiter must be a single ASCII character, got \"{}\"", d))
+    }
+}
+
+// The proxy set in the environment, checking the https variables first since the website is only served over https
+fn proxy_from_env() -> Option<String> {
+    ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|proxy| !proxy.trim().is_empty()))
+}
+
+// Parses a two-letter state code (i.e., "tx" becomes "TX")
+fn parse_state_code(state: &str) -> std::result::Result<String, String> {
+    let state: &str = state.trim();
+    if state.len() != 2 || !state.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("State code must be two letters, got \"{}\"", state))
+    }
+    Ok(state.to_uppercase())
+}
+
+// Parses a --since or --until date written as YYYY-MM-DD (i.e., "2024-01-31")
+fn parse_date(date: &str) -> std::result::Result<chrono::NaiveDate, String> {
+    chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Date must be written as YYYY-MM-DD (i.e., 2024-01-31), got \"{}\"", date))
+}
+
+// Parses a fraction greater than 0 and at most 1 (i.e., "0.1")
+fn parse_fraction(arg: &str) -> std::result::Result<f64, String> {
+    match arg.trim().parse::<f64>() {
+        Ok(fraction) if fraction > 0.0 && fraction <= 1.0 => Ok(fraction),
+        _ => Err(format!("\"{}\" is not a fraction greater than 0 and at most 1.", arg))
+    }
+}
+
+// Looks up a text encoding by its WHATWG label (i.e., "utf-8" or "windows-1252")
+fn parse_encoding(label: &str) -> std::result::Result<&'static encoding_rs::Encoding, String> {
+    encoding_rs::Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| format!("Unknown text encoding \"{}\". Use a label like \"utf-8\" or \"windows-1252\".", label))
+}
+
+// Same as parse_delimiter, except "auto" yields None so that the delimiter gets sniffed from each input file
+fn parse_input_delimiter(delimiter: &str) -> std::result::Result<Option<u8>, String> {
+    if delimiter == "auto" {
+        return Ok(None)
+    }
+    parse_delimiter(delimiter).map(Some)
+}
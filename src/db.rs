@@ -0,0 +1,502 @@
+use crate::error::{Error, Result};
+use crate::http::PageValidators;
+use crate::models::{BuyerSellerRelationship, WaterDetail};
+use crate::parse::{self, parse_population, PageData};
+
+// Includes necessary sql queries into the shipped exe
+static CREATE_SCHEMA_SQL: &str = include_str!("../src/queries/create_schema.sql");
+static INSERT_WATER_DETAIL_SQL: &str = include_str!("../src/queries/insert_water_detail.sql");
+static INSERT_BUYER_SELLER_RELATIONSHIP_SQL: &str = include_str!("../src/queries/insert_buyer_seller_relationship.sql");
+static UPSERT_WATER_DETAIL_SQL: &str = include_str!("../src/queries/upsert_water_detail.sql");
+static INSERT_WATER_DETAIL_IF_MISSING_SQL: &str = include_str!("../src/queries/insert_water_detail_if_missing.sql");
+static INSERT_BUYER_SELLER_RELATIONSHIP_IF_MISSING_SQL: &str = include_str!("../src/queries/insert_buyer_seller_relationship_if_missing.sql");
+static SELECT_PAGE_VALIDATORS_SQL: &str = include_str!("../src/queries/select_page_validators.sql");
+static INSERT_PAGE_VALIDATORS_SQL: &str = include_str!("../src/queries/insert_page_validators.sql");
+static INSERT_PAGE_HTML_SQL: &str = include_str!("../src/queries/insert_page_html.sql");
+static SELECT_PAGE_HTML_SQL: &str = include_str!("../src/queries/select_page_html.sql");
+static SELECT_WATER_DETAIL_SQL: &str = include_str!("../src/queries/select_water_detail.sql");
+static SELECT_RELATIONSHIPS_BY_SELLER_SQL: &str = include_str!("../src/queries/select_relationships_by_seller.sql");
+static SELECT_WATER_DETAILS_SQL: &str = include_str!("../src/queries/select_water_details.sql");
+static SELECT_RELATIONSHIPS_SQL: &str = include_str!("../src/queries/select_relationships.sql");
+static DELETE_RELATIONSHIPS_BY_SELLER_SQL: &str = include_str!("../src/queries/delete_relationships_by_seller.sql");
+static SELECT_SCRAPED_WATER_SYSTEM_NOS_SQL: &str = include_str!("../src/queries/select_scraped_water_system_nos.sql");
+static SELECT_WATER_DETAIL_EXISTS_SQL: &str = include_str!("../src/queries/select_water_detail_exists.sql");
+static UPDATE_BUYERS_SCRAPED_SQL: &str = include_str!("../src/queries/update_buyers_scraped.sql");
+static UPDATE_DEGREE_COUNTS_SQL: &str = include_str!("../src/queries/update_degree_counts.sql");
+static CREATE_CRAWL_VISITED_SQL: &str = include_str!("../src/queries/create_crawl_visited.sql");
+static INSERT_CRAWL_VISITED_SQL: &str = include_str!("../src/queries/insert_crawl_visited.sql");
+static REBUILD_WATER_BUYER_RELATIONSHIPS_SQL: &str = include_str!("../src/queries/rebuild_water_buyer_relationships.sql");
+
+// Used when --db isn't given. Relative to the working directory the scraper is run from.
+pub static DEFAULT_DATABASE_PATH: &str = "./water_buyer_relationships.db3";
+
+// Every column added to a table after the first released database, i.e., the one shipped as water_buyer_relationships.db3.
+// Databases created before a column existed get it added when they're opened (see migrate_schema). A new column goes
+// both here and in create_schema.sql, and must allow NULL, since the rows already stored have no value for it.
+static ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("water_systems", "activity_status", "TEXT"),
+    ("water_systems", "buyer_count", "INTEGER"),
+    ("water_systems", "seller_count", "INTEGER"),
+    ("water_systems", "source_water_type", "TEXT"),
+    ("water_systems", "buyers_scraped", "INTEGER"),
+    ("water_systems", "county", "TEXT"),
+    ("water_systems", "federal_source", "TEXT"),
+    ("water_systems", "address", "TEXT"),
+    ("water_systems", "city", "TEXT"),
+    ("water_systems", "zip", "TEXT"),
+    ("water_systems", "last_updated", "TEXT"),
+    ("water_systems", "scraped_at", "TEXT"),
+    ("water_buyer_relationships", "population_raw", "TEXT"),
+    ("water_buyer_relationships", "scraped_at", "TEXT")
+];
+
+// Opened once per run and passed to every query. Statements run for every row are prepared once
+// and reused through the connection's statement cache.
+// Creates the database and any missing tables, so a fresh checkout can run without a prepared database.
+// A database created by an older version is brought up to date (see migrate_schema).
+pub fn open(db_path: &std::path::Path) -> Result<rusqlite::Connection> {
+    let conn = rusqlite::Connection::open(db_path)?;
+    conn.execute_batch(CREATE_SCHEMA_SQL)?;
+    migrate_schema(&conn)?;
+    Ok(conn)
+}
+
+// Adds the columns in ADDED_COLUMNS that a table is missing. Older databases also declare the relationships'
+// population NOT NULL, which rolls back every page with a population that isn't a number (i.e., "N/A"). SQLite
+// can't drop a constraint, so that table is copied into a new one without it. Relationships stored before
+// population_raw existed get their population as the raw text. Nothing changes unless everything does.
+fn migrate_schema(conn: &rusqlite::Connection) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    let missing: Vec<(&str, &str, &str)> = missing_columns(&tx)?;
+    if !missing.is_empty() {
+        log::info!("Updating a database created by an older version of the scraper. Adding {} columns...", missing.len());
+    }
+    for (table, column, column_type) in missing {
+        log::debug!("Adding column {} to table {}...", column, table);
+        tx.execute_batch(&format!("alter table {} add column {} {};", table, column, column_type))?;
+    }
+    let population_not_null: bool = 
+        tx.query_row("select \"notnull\" from pragma_table_info('water_buyer_relationships') where name = 'population'", [], |row| row.get(0))?;
+    if population_not_null {
+        log::info!("Allowing a blank population in table water_buyer_relationships of the database...");
+        tx.execute_batch(REBUILD_WATER_BUYER_RELATIONSHIPS_SQL)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// The entries of ADDED_COLUMNS that the database doesn't have yet
+fn missing_columns(conn: &rusqlite::Connection) -> Result<Vec<(&'static str, &'static str, &'static str)>> {
+    let mut stmt = conn.prepare("select name from pragma_table_info(?1)")?;
+    let mut missing: Vec<(&'static str, &'static str, &'static str)> = Vec::new();
+    for (table, column, column_type) in ADDED_COLUMNS.iter().copied() {
+        let columns: std::collections::HashSet<String> = 
+            stmt.query_map([table], |row| row.get(0))?.collect::<rusqlite::Result<std::collections::HashSet<String>>>()?;
+        if !columns.contains(column) {
+            missing.push((table, column, column_type));
+        }
+    }
+    Ok(missing)
+}
+
+// Set by --fast-db. Write-ahead logging lets each page's transaction commit without rewriting the main file,
+// and synchronous=NORMAL only syncs at checkpoints. A crash can lose the last few pages, but never corrupts the database.
+pub fn enable_fast_writes(conn: &rusqlite::Connection) -> Result<()> {
+    let journal_mode: String = conn.query_row("pragma journal_mode = WAL", [], |row| row.get(0))?;
+    if !journal_mode.eq_ignore_ascii_case("wal") {
+        log::warn!("The database couldn't be switched to WAL mode (it is using \"{}\"). Writes may not be any faster.", journal_mode);
+    }
+    conn.execute_batch("pragma synchronous = NORMAL;")?;
+    Ok(())
+}
+
+// Used by --dry-run and export so that nothing can be written by mistake. Fails if the database doesn't exist.
+pub fn open_read_only(db_path: &std::path::Path) -> Result<rusqlite::Connection> {
+    if !db_path.exists() {
+        return Err(Error::Config(format!("Database file {} doesn't exist. Run a scrape to create it, or pass --db to use another database.", db_path.display())))
+    }
+    let conn = rusqlite::Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    // A read-only database can't be migrated, and its queries would fail on the missing columns
+    if !missing_columns(&conn)?.is_empty() {
+        return Err(Error::Config(format!("Database file {} was created by an older version of the scraper. Run a scrape or reparse with it once to update it.", db_path.display())))
+    }
+    Ok(conn)
+}
+
+// Stores the scraped water detail, every buyer found on its page, and their relationships.
+// buyers_scraped records whether the page's buyers were actually read, so that a system with no buyers
+// can be told apart from one whose buyers are unknown.
+// Everything from the page is written in one transaction. If any insert fails, nothing from the page is saved,
+// so the page validators are never stored for a page that was only partly saved.
+// stored_relationships holds the (buyer, seller) pairs already stored from a 'Buyers of Water' table during this run.
+// Those pairs are skipped instead of being written again, and the pairs from this page are added once it's saved.
+// With upsert (--upsert), water systems that are already stored are updated in place instead of replaced. See insert_water_detail.
+pub fn store_page(
+    page: &PageData, 
+    buyers_scraped: bool, 
+    upsert: bool,
+    conn: &mut rusqlite::Connection, 
+    created_timestamp: &String,
+    stored_relationships: &mut std::collections::HashSet<(String, String)>
+) -> Result<()> {
+    let scraped_at: String = scraped_at_now();
+    let tx = conn.transaction()?;
+    let new_relationships: Vec<(String, String)> = insert_page(&tx, page, buyers_scraped, upsert, created_timestamp, &scraped_at, stored_relationships)?;
+    tx.commit()?;
+    stored_relationships.extend(new_relationships);
+    Ok(())
+}
+
+// Same as store_page, but first removes the relationships stored from an earlier copy of the page,
+// so that relationships no longer on the page are not left behind.
+// scraped_at is set to the time of the replacement, since when the stored copy was fetched isn't kept to the second.
+pub fn replace_page(page: &PageData, buyers_scraped: bool, conn: &mut rusqlite::Connection, created_timestamp: &String) -> Result<()> {
+    let scraped_at: String = scraped_at_now();
+    let tx = conn.transaction()?;
+    tx.execute(DELETE_RELATIONSHIPS_BY_SELLER_SQL, rusqlite::named_params! {
+        ":seller": page.water_detail.ws_number
+    })?;
+    insert_page(&tx, page, buyers_scraped, false, created_timestamp, &scraped_at, &std::collections::HashSet::new())?;
+    tx.commit()?;
+    Ok(())
+}
+
+// The time a page is stored at, recorded in the scraped_at column of every row written from it.
+// ISO-8601 in UTC to the second (i.e., "2024-03-15T15:22:00Z"), so that it sorts as text.
+fn scraped_at_now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+// Returns the (buyer, seller) pairs inserted from the 'Buyers of Water' table, leaving out those in stored_relationships
+fn insert_page(
+    conn: &rusqlite::Connection, 
+    page: &PageData, 
+    buyers_scraped: bool, 
+    upsert: bool,
+    created_timestamp: &String,
+    scraped_at: &str,
+    stored_relationships: &std::collections::HashSet<(String, String)>
+) -> Result<Vec<(String, String)>> {
+    let root_water_detail: &WaterDetail = &page.water_detail;
+    // The key for the hash map is the water detail number string
+    let mut parsed_water_details: std::collections::HashMap<String, WaterDetail> = std::collections::HashMap::new();
+    parsed_water_details.insert(root_water_detail.ws_number.clone(), root_water_detail.clone());
+    log::debug!("Adding water detail {} if it doesn't already exist...", root_water_detail.ws_number);
+    insert_water_detail(conn, root_water_detail, upsert, created_timestamp, scraped_at)?;
+    conn.execute(UPDATE_BUYERS_SCRAPED_SQL, rusqlite::named_params! {
+        ":water_system_no": root_water_detail.ws_number,
+        ":buyers_scraped": buyers_scraped
+    })?;
+    log::debug!("Added water detail {}", root_water_detail.ws_number);
+
+    // Only the buyer's name is known from this table, so a buyer that is already stored (possibly from its own page)
+    // is left alone instead of having its details and buyers_scraped wiped out.
+    log::debug!("Adding all water details found within the 'Buyers of Water' table...");
+    for r in page.relationships.iter() {
+        if !parsed_water_details.contains_key(&r.buyer) {
+            let wd = WaterDetail {
+                ws_number: r.buyer.clone(),
+                st_code: parse::state_code_of(&r.buyer),
+                name: Some(r.buyer_name.clone()),
+                ..Default::default()
+            };
+            insert_water_detail_if_missing(conn, &wd, created_timestamp, scraped_at)?;
+            parsed_water_details.insert(wd.ws_number.clone(), wd);
+        }
+    }
+    log::debug!("Added all water details found within the 'Buyers of Water' table.");
+    log::debug!("Adding all relationships found within the 'Buyers of Water' table...");
+    // Insert new buyer/seller relationships into database
+    let mut new_relationships: Vec<(String, String)> = Vec::new();
+    for r in page.relationships.iter() {
+        let key: (String, String) = (r.buyer.clone(), r.seller.clone());
+        if stored_relationships.contains(&key) {
+            log::trace!("Skipped relationship {} -> {} because it was already stored during this run.", r.seller, r.buyer);
+            continue;
+        }
+        insert_buyer_seller_relationship(conn, r, created_timestamp, scraped_at)?;
+        new_relationships.push(key);
+    }
+    log::debug!("Added all relationships found within the 'Buyers of Water' table.");
+
+    // The same relationship is listed on the seller's page too, whose buyers table is taken as the source of truth.
+    // Sellers and relationships from this table are only added if they aren't stored yet, so whichever page is scraped
+    // first, the edge is stored once and the seller's own page has the final say.
+    log::debug!("Adding all water details and relationships found within the 'Sellers of Water' table...");
+    for r in page.seller_relationships.iter() {
+        if !parsed_water_details.contains_key(&r.seller) {
+            let wd = WaterDetail {
+                ws_number: r.seller.clone(),
+                st_code: parse::state_code_of(&r.seller),
+                name: Some(r.seller_name.clone()),
+                ..Default::default()
+            };
+            insert_water_detail_if_missing(conn, &wd, created_timestamp, scraped_at)?;
+            parsed_water_details.insert(wd.ws_number.clone(), wd);
+        }
+        if !stored_relationships.contains(&(r.buyer.clone(), r.seller.clone())) {
+            insert_buyer_seller_relationship_if_missing(conn, r, created_timestamp, scraped_at)?;
+        }
+    }
+    log::debug!("Added all water details and relationships found within the 'Sellers of Water' table.");
+    if page.validators.etag.is_some() || page.validators.last_modified.is_some() {
+        insert_page_validators(conn, &root_water_detail.ws_number, &page.validators, created_timestamp)?;
+    }
+    Ok(new_relationships)
+}
+
+// Archives the raw HTML of a scraped page so it can be parsed again later without a request.
+// Only the latest copy of each page is kept.
+pub fn store_page_html(ws_number: &String, html: &str, conn: &rusqlite::Connection, created_timestamp: &String) -> Result<usize> {
+    Ok(conn.execute(INSERT_PAGE_HTML_SQL, rusqlite::named_params! {
+        ":water_system_no": ws_number,
+        ":html": html.as_bytes(),
+        ":created_timestamp": created_timestamp
+    })?)
+}
+
+// Every page archived by --store-html, as (water system number, raw HTML)
+pub fn select_page_html(conn: &rusqlite::Connection) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut stmt = conn.prepare(SELECT_PAGE_HTML_SQL)?;
+    let pages = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(pages.collect::<rusqlite::Result<Vec<(String, Vec<u8>)>>>()?)
+}
+
+// Water system numbers whose own page has been scraped before. Systems only stored as the buyer of another system are left out.
+pub fn select_scraped_ws_numbers(conn: &rusqlite::Connection) -> Result<std::collections::HashSet<String>> {
+    let mut stmt = conn.prepare(SELECT_SCRAPED_WATER_SYSTEM_NOS_SQL)?;
+    let ws_numbers = stmt.query_map([], |row| row.get(0))?;
+    Ok(ws_numbers.collect::<rusqlite::Result<std::collections::HashSet<String>>>()?)
+}
+
+// Sets up the list of water systems a --max-depth crawl has queued during this run, so that a cycle of buyers is never
+// followed twice. It's a temp table, so it can grow as large as the crawl without being held in memory,
+// works on a read-only connection (--dry-run), and is dropped along with the connection.
+pub fn create_crawl_visited(conn: &rusqlite::Connection) -> Result<()> {
+    conn.execute_batch(CREATE_CRAWL_VISITED_SQL)?;
+    Ok(())
+}
+
+// Marks a water system as queued by the crawl. Returns false if it already was.
+pub fn mark_crawl_visited(ws_number: &str, conn: &rusqlite::Connection) -> Result<bool> {
+    let mut stmt = conn.prepare_cached(INSERT_CRAWL_VISITED_SQL)?;
+    Ok(stmt.execute(rusqlite::named_params! { ":water_system_no": ws_number })? == 1)
+}
+
+// Whether the water system's own page has been stored. Since a page is stored in a single transaction,
+// this also means its relationships were stored.
+pub fn water_detail_exists(ws_number: &String, conn: &rusqlite::Connection) -> Result<bool> {
+    let mut stmt = conn.prepare_cached(SELECT_WATER_DETAIL_EXISTS_SQL)?;
+    Ok(stmt.query_row(rusqlite::named_params! { ":water_system_no": ws_number }, |row| row.get(0))?)
+}
+
+pub fn select_water_detail(
+    ws_number: &String, 
+    conn: &rusqlite::Connection
+) -> Result<Option<WaterDetail>> {
+    let mut stmt = conn.prepare_cached(SELECT_WATER_DETAIL_SQL)?;
+    let mut rows = stmt.query(rusqlite::named_params! {
+        ":water_system_no": ws_number
+    })?;
+    match rows.next()? {
+        Some(row) => Ok(Some(WaterDetail {
+            ws_number: row.get(0)?,
+            name: row.get(1)?,
+            st_code: row.get(2)?,
+            is_number: row.get(3)?,
+            activity_status: None,
+            source_water_type: None,
+            county: None,
+            federal_source: None,
+            address: None,
+            city: None,
+            zip: None,
+            last_updated: None,
+            source_row: None
+        })),
+        None => Ok(None)
+    }
+}
+
+// Every stored relationship in which the given water system is the seller, ordered by buyer.
+// Populations come back as the original cell text, and unknown availability codes come back as empty strings.
+pub fn select_relationships_by_seller(
+    seller: &String, 
+    conn: &rusqlite::Connection
+) -> Result<Vec<BuyerSellerRelationship>> {
+    let mut stmt = conn.prepare_cached(SELECT_RELATIONSHIPS_BY_SELLER_SQL)?;
+    let relationships = stmt.query_map(rusqlite::named_params! { ":seller": seller }, relationship_from_row)?;
+    Ok(relationships.collect::<rusqlite::Result<Vec<BuyerSellerRelationship>>>()?)
+}
+
+// Every stored relationship, ordered by seller and then buyer
+pub fn select_relationships(conn: &rusqlite::Connection) -> Result<Vec<BuyerSellerRelationship>> {
+    let mut stmt = conn.prepare(SELECT_RELATIONSHIPS_SQL)?;
+    let relationships = stmt.query_map([], relationship_from_row)?;
+    Ok(relationships.collect::<rusqlite::Result<Vec<BuyerSellerRelationship>>>()?)
+}
+
+// Columns: seller, seller name, buyer, buyer name, raw population, availability code
+fn relationship_from_row(row: &rusqlite::Row) -> rusqlite::Result<BuyerSellerRelationship> {
+    Ok(BuyerSellerRelationship {
+        seller: row.get(0)?,
+        seller_name: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+        buyer: row.get(2)?,
+        buyer_name: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        population: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        availability: row.get::<_, Option<String>>(5)?.unwrap_or_default()
+    })
+}
+
+// Every stored water detail, including the ones only known as the buyer or seller of another system
+pub fn select_water_details(conn: &rusqlite::Connection) -> Result<Vec<WaterDetail>> {
+    let mut stmt = conn.prepare(SELECT_WATER_DETAILS_SQL)?;
+    let water_details = stmt.query_map([], |row| {
+        Ok(WaterDetail {
+            ws_number: row.get(0)?,
+            name: row.get(1)?,
+            st_code: row.get(2)?,
+            is_number: row.get(3)?,
+            activity_status: row.get(4)?,
+            source_water_type: row.get(5)?,
+            county: row.get(6)?,
+            federal_source: row.get(7)?,
+            address: row.get(8)?,
+            city: row.get(9)?,
+            zip: row.get(10)?,
+            last_updated: row.get(11)?,
+            source_row: None
+        })
+    })?;
+    Ok(water_details.collect::<rusqlite::Result<Vec<WaterDetail>>>()?)
+}
+
+// Recounts how many buyers and sellers every water system has from the stored relationships.
+// buyer_count is left empty for systems whose buyers were never scraped.
+// Every row is recounted, so running this again is always safe. Returns the number of water systems updated.
+pub fn update_degree_counts(conn: &rusqlite::Connection) -> Result<usize> {
+    Ok(conn.execute(UPDATE_DEGREE_COUNTS_SQL, [])?)
+}
+
+// Replaces an already stored water detail by default. With upsert, the stored row is updated instead: its created date, 
+// buyer and seller counts are kept, and fields this water detail doesn't have (i.e., an address missing from the new copy of the page) are left alone.
+fn insert_water_detail(
+    conn: &rusqlite::Connection, 
+    water_detail: &WaterDetail, 
+    upsert: bool,
+    created_timestamp: &String,
+    scraped_at: &str
+) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(if upsert { UPSERT_WATER_DETAIL_SQL } else { INSERT_WATER_DETAIL_SQL })?;
+    Ok(stmt.insert(rusqlite::named_params! {
+        ":water_system_no": water_detail.ws_number,
+        ":water_system_name": water_detail.name,
+        ":state_code": water_detail.st_code,
+        ":is_no": water_detail.is_number,
+        ":activity_status": water_detail.activity_status,
+        ":source_water_type": water_detail.source_water_type,
+        ":county": water_detail.county,
+        ":federal_source": water_detail.federal_source,
+        ":address": water_detail.address,
+        ":city": water_detail.city,
+        ":zip": water_detail.zip,
+        ":last_updated": water_detail.last_updated,
+        ":created_timestamp": created_timestamp,
+        ":scraped_at": scraped_at
+    })?)
+}
+
+fn insert_buyer_seller_relationship(
+    conn: &rusqlite::Connection, 
+    relationship: &BuyerSellerRelationship,
+    created_timestamp: &String,
+    scraped_at: &str
+) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(INSERT_BUYER_SELLER_RELATIONSHIP_SQL)?;
+    Ok(stmt.insert(rusqlite::named_params! {
+        ":seller": relationship.seller,
+        ":buyer": relationship.buyer,
+        ":population": parse_population(&relationship.population),
+        ":population_raw": relationship.population,
+        ":availability": relationship.availability,
+        ":created_timestamp": created_timestamp,
+        ":scraped_at": scraped_at
+    })?)
+}
+
+// Leaves an already stored water detail alone. Returns the number of rows inserted (0 or 1).
+fn insert_water_detail_if_missing(
+    conn: &rusqlite::Connection, 
+    water_detail: &WaterDetail, 
+    created_timestamp: &String,
+    scraped_at: &str
+) -> Result<usize> {
+    let mut stmt = conn.prepare_cached(INSERT_WATER_DETAIL_IF_MISSING_SQL)?;
+    Ok(stmt.execute(rusqlite::named_params! {
+        ":water_system_no": water_detail.ws_number,
+        ":water_system_name": water_detail.name,
+        ":state_code": water_detail.st_code,
+        ":is_no": water_detail.is_number,
+        ":activity_status": water_detail.activity_status,
+        ":source_water_type": water_detail.source_water_type,
+        ":county": water_detail.county,
+        ":federal_source": water_detail.federal_source,
+        ":address": water_detail.address,
+        ":city": water_detail.city,
+        ":zip": water_detail.zip,
+        ":last_updated": water_detail.last_updated,
+        ":created_timestamp": created_timestamp,
+        ":scraped_at": scraped_at
+    })?)
+}
+
+// Leaves an already stored relationship alone. Returns the number of rows inserted (0 or 1).
+fn insert_buyer_seller_relationship_if_missing(
+    conn: &rusqlite::Connection, 
+    relationship: &BuyerSellerRelationship,
+    created_timestamp: &String,
+    scraped_at: &str
+) -> Result<usize> {
+    let mut stmt = conn.prepare_cached(INSERT_BUYER_SELLER_RELATIONSHIP_IF_MISSING_SQL)?;
+    Ok(stmt.execute(rusqlite::named_params! {
+        ":seller": relationship.seller,
+        ":buyer": relationship.buyer,
+        ":population": parse_population(&relationship.population),
+        ":population_raw": relationship.population,
+        ":availability": relationship.availability,
+        ":created_timestamp": created_timestamp,
+        ":scraped_at": scraped_at
+    })?)
+}
+
+pub fn select_page_validators(
+    ws_number: &String, 
+    conn: &rusqlite::Connection
+) -> Result<Option<PageValidators>> {
+    let mut stmt = conn.prepare_cached(SELECT_PAGE_VALIDATORS_SQL)?;
+    let mut rows = stmt.query(rusqlite::named_params! {
+        ":water_system_no": ws_number
+    })?;
+    match rows.next()? {
+        Some(row) => Ok(Some(PageValidators {
+            etag: row.get(0)?,
+            last_modified: row.get(1)?
+        })),
+        None => Ok(None)
+    }
+}
+
+fn insert_page_validators(
+    conn: &rusqlite::Connection, 
+    ws_number: &String, 
+    validators: &PageValidators, 
+    created_timestamp: &String
+) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(INSERT_PAGE_VALIDATORS_SQL)?;
+    Ok(stmt.insert(rusqlite::named_params! {
+        ":water_system_no": ws_number,
+        ":etag": validators.etag,
+        ":last_modified": validators.last_modified,
+        ":created_timestamp": created_timestamp
+    })?)
+}
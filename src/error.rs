@@ -0,0 +1,55 @@
+// Every way the scraper can fail. Messages are shown to the user as-is, so they should say what to fix.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    // Bad arguments or an input file that can't be used
+    #[error("{0}")]
+    Config(String),
+    #[error("Unable to read input file {path}. {source}")]
+    InputIo { path: std::path::PathBuf, source: std::io::Error },
+    #[error("Input file {path} is not a valid csv. {source}")]
+    InputCsv { path: std::path::PathBuf, source: csv::Error },
+    #[error("{context} {source}")]
+    InputJson { context: String, source: serde_json::Error },
+    #[error("Cannot write to {path}: {source}")]
+    OutputFile { path: std::path::PathBuf, source: std::io::Error },
+    #[error("Unable to create page cache directory {path}. {source}")]
+    CacheDir { path: std::path::PathBuf, source: std::io::Error },
+    #[error("Unable to write output. {0}")]
+    Output(#[from] std::io::Error),
+
+    // The request never got a response
+    #[error("Request was unsuccessful. {0}")]
+    Request(#[from] minreq::Error),
+    // The response status was not 2xx
+    // retry_after is the wait the website asked for, if any (see http::parse_retry_after)
+    #[error("Response status was not OK. Status code: {status_code} | Reason: {reason_phrase}")]
+    Status { status_code: i32, reason_phrase: String, retry_after: Option<std::time::Duration> },
+    // Not a failure. The page hasn't changed since the validators were stored, so there is nothing to parse.
+    #[error("Page has not changed since it was last scraped.")]
+    NotModified,
+
+    // The page came back, but not in the shape the parser expects
+    #[error("TCEQ page structure may have changed - parser selectors need updating. Could not find {missing} on the page for {ws_number} ({url}).")]
+    PageStructure { missing: String, ws_number: String, url: String },
+
+    #[error("Database error. {0}")]
+    Database(#[from] rusqlite::Error),
+
+    // Returned once everything scraped before the Ctrl-C is saved, so the run still exits with a failure
+    #[error("Stopped by Ctrl-C. Everything scraped before then was saved.")]
+    Interrupted
+}
+
+impl Error {
+    // Failures that may go away if the request is sent again: no response at all, a server error, or rate limiting.
+    // Anything else (i.e., a 404) would fail the same way again.
+    pub fn is_transient(& self) -> bool {
+        match self {
+            Error::Request(_) => true,
+            Error::Status { status_code, .. } => *status_code >= 500 || *status_code == 429,
+            _ => false
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
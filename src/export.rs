@@ -0,0 +1,27 @@
+use crate::cli::{ExportConfig, ExportTable};
+use crate::db;
+use crate::error::{Error, Result};
+use crate::output::{self, OutputWriter};
+
+// Writes a table of the database to the export file, without sending any requests.
+// The database is opened read-only, so an export never changes it.
+pub fn run(config: &ExportConfig) -> Result<()> {
+    let conn = db::open_read_only(&config.db_path)?;
+    let mut writer: OutputWriter = output::open_export_writer(config)?;
+    let records_written: usize = 
+        match config.table {
+            ExportTable::Details => write_all(&mut writer, &db::select_water_details(&conn)?)?,
+            ExportTable::Relationships => write_all(&mut writer, &db::select_relationships(&conn)?)?
+        };
+    output::finish(writer)?;
+    conn.close().map_err(|(_, e)| Error::Database(e))?;
+    log::info!("Exported {} {}.", records_written, if config.table == ExportTable::Details { "water details" } else { "relationships" });
+    Ok(())
+}
+
+fn write_all<T: serde::Serialize>(writer: &mut OutputWriter, records: &[T]) -> Result<usize> {
+    for record in records.iter() {
+        output::write_record(writer, record)?;
+    }
+    Ok(records.len())
+}
@@ -0,0 +1,342 @@
+use crate::cli::Config;
+use crate::error::{Error, Result};
+use crate::models::WaterDetail;
+use crate::parse::{normalize_ws_number, water_detail_from_url};
+
+// What to do when more than one input column matches the same mapped header
+#[derive(Debug, Clone, Copy)]
+pub enum DuplicateColumns {
+    Error,
+    PickFirst,
+    PickLast
+}
+
+// How many input rows --sample or --sample-fraction picks
+#[derive(Debug, Clone, Copy)]
+pub enum SampleSize {
+    Count(usize),
+    Fraction(f64) // Between 0 and 1, rounded up to a whole row
+}
+
+// Every water detail to scrape, in input order, along with how many input rows were left out
+pub struct InputRows {
+    pub water_details: Vec<WaterDetail>,
+    pub blank_rows_skipped: usize,
+    pub duplicate_rows_skipped: usize,
+    pub malformed_rows_skipped: usize,
+    pub other_state_rows_skipped: usize, // Rows outside --state
+    pub limited_rows_skipped: usize, // Rows past --limit
+    pub unsampled_rows_skipped: usize
+}
+
+// Reads every input file in order. Blank rows are skipped, and a water system listed more than once is only kept the first time.
+pub fn read_water_details(config: &Config) -> Result<InputRows> {
+    let mut input_rows = InputRows {
+        water_details: Vec::new(),
+        blank_rows_skipped: 0,
+        duplicate_rows_skipped: 0,
+        malformed_rows_skipped: 0,
+        other_state_rows_skipped: 0,
+        limited_rows_skipped: 0,
+        unsampled_rows_skipped: 0
+    };
+    let mut input_ws_numbers: std::collections::HashSet<String> = std::collections::HashSet::new();
+    for input_file_path in config.input_file_paths.iter() {
+        let contents: String = read_input_text(input_file_path, config.input_encoding)?;
+        let file_water_details: Vec<WaterDetail> = 
+            if config.is_json_input {
+                read_json_input(input_file_path, &contents)?
+            }
+            else if config.is_url_input {
+                let (url_water_details, malformed_rows): (Vec<WaterDetail>, usize) = read_url_input(input_file_path, &contents);
+                input_rows.malformed_rows_skipped += malformed_rows;
+                url_water_details
+            }
+            else {
+                let input_delimiter: u8 = config.input_delimiter.unwrap_or_else(|| sniff_delimiter(&contents));
+                let (csv_water_details, malformed_rows): (Vec<WaterDetail>, usize) = read_csv_input(input_file_path, &contents, input_delimiter, config)?;
+                input_rows.malformed_rows_skipped += malformed_rows;
+                csv_water_details
+            };
+        for mut detail in file_water_details {
+            // Hand-assembled files often end with blank rows (i.e., ",,"), which have nothing to scrape
+            if detail.is_number.as_deref().unwrap_or("").trim().is_empty() && detail.st_code.trim().is_empty() && detail.ws_number.trim().is_empty() {
+                input_rows.blank_rows_skipped += 1;
+                continue;
+            }
+            detail.ws_number = normalize_ws_number(&detail.ws_number);
+            // A blank IS number is looked up before scraping (see main), instead of failing the row
+            detail.is_number = detail.is_number.filter(|is_number| !is_number.trim().is_empty());
+            // The fields go straight into the page URL, so a row that can't make a valid URL is never requested
+            if let Err(reason) = check_water_detail(&detail) {
+                match detail.source_row {
+                    Some(line) => log::warn!("Skipping water detail \"{}\" from line {} of {}: {}", detail.ws_number, line, input_file_path.display(), reason),
+                    None => log::warn!("Skipping water detail \"{}\" from {}: {}", detail.ws_number, input_file_path.display(), reason)
+                }
+                input_rows.malformed_rows_skipped += 1;
+                continue;
+            }
+            // The same water system may be listed in more than one input file
+            if !input_ws_numbers.insert(detail.ws_number.clone()) {
+                input_rows.duplicate_rows_skipped += 1;
+                continue;
+            }
+            input_rows.water_details.push(detail);
+        }
+    }
+    if !config.states.is_empty() {
+        let input_len: usize = input_rows.water_details.len();
+        input_rows.water_details.retain(|detail| config.states.iter().any(|state| state.eq_ignore_ascii_case(detail.st_code.trim())));
+        input_rows.other_state_rows_skipped = input_len - input_rows.water_details.len();
+        if input_rows.water_details.is_empty() {
+            log::warn!("None of the {} input rows are from {}. There is nothing to scrape.", input_len, config.states.join(", "));
+        }
+        else {
+            log::info!("Scraping {} rows from {}. Skipping {} rows from other states.", input_rows.water_details.len(), config.states.join(", "), input_rows.other_state_rows_skipped);
+        }
+    }
+    if let Some(limit) = config.limit.filter(|limit| *limit < input_rows.water_details.len()) {
+        input_rows.limited_rows_skipped = input_rows.water_details.len() - limit;
+        input_rows.water_details.truncate(limit);
+        log::info!("Scraping the first {} rows. Skipping the remaining {}.", limit, input_rows.limited_rows_skipped);
+    }
+    if let Some(sample_size) = config.sample {
+        sample_water_details(&mut input_rows, sample_size, config.sample_seed);
+    }
+    Ok(input_rows)
+}
+
+// Checks that a water detail has what its page URL needs, apart from the IS number, which can be looked up.
+// Returns why it doesn't otherwise.
+fn check_water_detail(detail: &WaterDetail) -> std::result::Result<(), String> {
+    if detail.st_code.len() != 2 || !detail.st_code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("The state code \"{}\" is not two letters.", detail.st_code))
+    }
+    if detail.ws_number.is_empty() {
+        return Err("The water system number is blank.".to_string())
+    }
+    Ok(())
+}
+
+// Keeps a random subset of the water details, in input order. The same seed always picks the same rows from the same input.
+// Without a seed, one is picked at random and printed so the sample can be repeated.
+fn sample_water_details(input_rows: &mut InputRows, sample_size: SampleSize, seed: Option<u64>) {
+    let input_len: usize = input_rows.water_details.len();
+    let sample_len: usize = 
+        match sample_size {
+            SampleSize::Count(count) => count,
+            SampleSize::Fraction(fraction) => (input_len as f64 * fraction).ceil() as usize
+        }
+        .min(input_len);
+    let seed: u64 = seed.unwrap_or_else(rand::random);
+    log::info!("Sampling {} of {} rows (seed {}). Pass --sample-seed {} to pick the same rows again.", sample_len, input_len, seed, seed);
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut sampled_indexes: Vec<usize> = rand::seq::index::sample(&mut rng, input_len, sample_len).into_vec();
+    sampled_indexes.sort_unstable();
+    let mut water_details: Vec<WaterDetail> = std::mem::take(&mut input_rows.water_details);
+    input_rows.water_details = 
+        sampled_indexes
+            .iter()
+            .map(|idx| std::mem::take(&mut water_details[*idx]))
+            .collect();
+    input_rows.unsampled_rows_skipped = input_len - sample_len;
+    for (idx, detail) in sampled_indexes.iter().zip(input_rows.water_details.iter()) {
+        log::debug!("Sampled row {}: {}", idx+1, detail.ws_number);
+    }
+}
+
+// Reads an input file into UTF-8 text, decoding it from the given encoding.
+// A byte order mark, if present, takes priority over the given encoding.
+fn read_input_text(input_file_path: &std::path::Path, encoding: &'static encoding_rs::Encoding) -> Result<String> {
+    let bytes: Vec<u8> = 
+        std::fs::read(input_file_path)
+            .map_err(|source| Error::InputIo { path: input_file_path.to_path_buf(), source })?;
+    let (contents, used_encoding, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        log::warn!("Input file {} contains characters that aren't valid {}. They were replaced with \"\u{FFFD}\". Try a different --input-encoding.", 
+            input_file_path.display(), used_encoding.name());
+    }
+    Ok(contents.into_owned())
+}
+
+// Guesses the delimiter by counting candidate characters in the header row. Falls back to a comma.
+fn sniff_delimiter(contents: &str) -> u8 {
+    let header_line: &str = contents.lines().next().unwrap_or("");
+    [b',', b'\t', b';', b'|']
+        .into_iter()
+        .map(|candidate| (candidate, header_line.bytes().filter(|b| *b == candidate).count()))
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map(|(candidate, _)| candidate)
+        .unwrap_or(b',')
+}
+
+// Reads water details from a csv file, mapping columns by the header names supplied in the arguments.
+// Rows that can't be read are logged and skipped. Returns the water details along with the number of rows skipped.
+fn read_csv_input(
+    input_file_path: &std::path::Path, 
+    contents: &str,
+    input_delimiter: u8, 
+    config: &Config
+) -> Result<(Vec<WaterDetail>, usize)> {
+    let is_header_arg: &String = &config.is_header;
+    let st_header_arg: &String = &config.st_header;
+    let ws_header_arg: &String = &config.ws_header;
+    log::info!("Reading headers from {}...", input_file_path.display());
+    let mut reader = 
+        csv::ReaderBuilder::new()
+            .delimiter(input_delimiter)
+            .from_reader(contents.as_bytes());
+    // Every column that matches a mapped header. More than one column for the same header means the file is ambiguous.
+    let mut header_matches: std::collections::BTreeMap<String, Vec<usize>> = std::collections::BTreeMap::new();
+    for (idx, header) in reader.headers().map_err(|source| Error::InputCsv { path: input_file_path.to_path_buf(), source })?.iter().enumerate() {
+        let h: String = header.to_string();
+        if h == *st_header_arg || h == *ws_header_arg || h == *is_header_arg {
+            header_matches.entry(h).or_default().push(idx);
+        }
+        else {
+            log::debug!("Ignoring column \"{}\" (column {}) of {} since it isn't mapped by the -w, -n, or -s arguments.", h, idx+1, input_file_path.display());
+        }
+    }
+    let mut header_map: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for (header, indexes) in header_matches {
+        let idx: usize = 
+            match (indexes.as_slice(), config.duplicate_columns) {
+                ([only], _) => *only,
+                (_, DuplicateColumns::Error) => {
+                    return Err(Error::Config(format!("Input file {} has more than one \"{}\" column (columns {}). Remove the extra columns, or pass --pick-first or --pick-last to choose one.", 
+                        input_file_path.display(), header, column_list(&indexes))))
+                },
+                (_, DuplicateColumns::PickFirst) => indexes[0],
+                (_, DuplicateColumns::PickLast) => indexes[indexes.len()-1]
+            };
+        if indexes.len() > 1 {
+            log::warn!("Input file {} has more than one \"{}\" column (columns {}). Using column {}.", 
+                input_file_path.display(), header, column_list(&indexes), idx+1);
+        }
+        header_map.insert(header, idx);
+    }
+    
+    // In case there are headers missing from the input,
+    // show the user which headers are missing.
+    if header_map.len() != 3 {
+        let mut missing_headers_list: Vec<String> = Vec::new();
+        if !header_map.contains_key(is_header_arg) {
+            missing_headers_list.push(is_header_arg.clone());
+        }
+        if !header_map.contains_key(st_header_arg) {
+            missing_headers_list.push(st_header_arg.clone());
+        }
+        if !header_map.contains_key(ws_header_arg) {
+            missing_headers_list.push(ws_header_arg.clone());
+        }
+        let missing_headers: String = 
+            missing_headers_list
+                .iter_mut()
+                .fold("".to_string(), |mut acc, h| {
+                    if !acc.is_empty() {
+                        acc.push_str(", ");
+                    }
+                    acc.push_str(h);
+                    acc
+                });
+        return Err(Error::Config(format!("Missing headers from input file {}: {}. Double check the header names that were supplied to the -w, -n, and -s arguments.", input_file_path.display(), missing_headers)))
+    }
+    log::info!("Headers successfully read.");
+
+    log::info!("Reading rows from {}...", input_file_path.display());
+    let mut water_details: Vec<WaterDetail> = Vec::new();
+    let mut malformed_rows: usize = 0;
+    for (row_idx, record) in reader.records().enumerate() {
+        // The line the row starts on in the file, counting the header row. Quoted fields can span more than one line.
+        // Falls back to assuming one line per row if the reader doesn't know.
+        let fallback_line: usize = row_idx + 2;
+        let record: csv::StringRecord = 
+            match record {
+                Ok(record) => record,
+                Err(e) => {
+                    let line: usize = e.position().map_or(fallback_line, |position| position.line() as usize);
+                    log::warn!("Skipping line {} of {}: {}", line, input_file_path.display(), e);
+                    malformed_rows += 1;
+                    continue;
+                }
+            };
+        let line: usize = record.position().map_or(fallback_line, |position| position.line() as usize);
+        let field = |header: &String| record.get(header_map[header]).map(|f| f.to_string());
+        let (Some(is_number), Some(st_code), Some(ws_number)) = (field(is_header_arg), field(st_header_arg), field(ws_header_arg)) else {
+            log::warn!("Skipping line {} of {}: The row is missing one of the -w, -n, or -s columns.", line, input_file_path.display());
+            malformed_rows += 1;
+            continue;
+        };
+        water_details.push(WaterDetail {
+            is_number: Some(is_number),
+            st_code,
+            ws_number,
+            name: None, // Name gets scraped from the page
+            activity_status: None, // Activity status gets scraped from the page
+            source_water_type: None, // Source water type gets scraped from the page
+            county: None, // County gets scraped from the page
+            federal_source: None, // Federal primary source gets scraped from the page
+            address: None, // Address, city, and zip code get scraped from the page
+            city: None,
+            zip: None,
+            last_updated: None, // Last updated date gets scraped from the page
+            source_row: Some(line)
+        });
+    }
+    log::info!("Rows successfully read.");
+    Ok((water_details, malformed_rows))
+}
+
+// Lists zero-based column indexes as one-based column numbers (i.e., "2, 5")
+fn column_list(indexes: &[usize]) -> String {
+    indexes.iter().map(|idx| (idx+1).to_string()).collect::<Vec<String>>().join(", ")
+}
+
+// Reads water details from either a JSON array or newline-delimited JSON objects.
+// Only is_number, st_code, and ws_number are expected; header mapping does not apply.
+fn read_json_input(input_file_path: &std::path::Path, contents: &str) -> Result<Vec<WaterDetail>> {
+    log::info!("Reading rows from {}...", input_file_path.display());
+    let water_details: Vec<WaterDetail> = 
+        if contents.trim_start().starts_with('[') {
+            serde_json::from_str(contents)
+                .map_err(|source| Error::InputJson { context: format!("Input file {} is not a valid JSON array of water details.", input_file_path.display()), source })?
+        }
+        else {
+            contents
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(line_idx, line)| {
+                    serde_json::from_str(line)
+                        .map(|detail: WaterDetail| WaterDetail { source_row: Some(line_idx+1), ..detail })
+                        .map_err(|source| Error::InputJson { context: format!("Line {} of input file {} is not a valid water detail.", line_idx+1, input_file_path.display()), source })
+                })
+                .collect::<Result<Vec<WaterDetail>>>()?
+        };
+    log::info!("Rows successfully read.");
+    Ok(water_details)
+}
+
+// Reads water details from a text file of water system detail page URLs, one per line.
+// Lines that aren't a detail page URL are logged and skipped. Returns the water details along with the number of lines skipped.
+fn read_url_input(input_file_path: &std::path::Path, contents: &str) -> (Vec<WaterDetail>, usize) {
+    log::info!("Reading URLs from {}...", input_file_path.display());
+    let mut water_details: Vec<WaterDetail> = Vec::new();
+    let mut malformed_lines: usize = 0;
+    for (line_idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match water_detail_from_url(line) {
+            Some(detail) => water_details.push(WaterDetail { source_row: Some(line_idx+1), ..detail }),
+            None => {
+                log::warn!("Skipping line {} of {}: \"{}\" is not a water system detail URL with tinwsys_is_number, tinwsys_st_code, and wsnumber.", 
+                    line_idx+1, input_file_path.display(), line.trim());
+                malformed_lines += 1;
+            }
+        }
+    }
+    log::info!("URLs successfully read.");
+    (water_details, malformed_lines)
+}
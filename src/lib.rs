@@ -0,0 +1,15 @@
+// Scraping, parsing, and storage for water buyer relationships on the TCEQ Drinking Water Watch website.
+// The tceq-scraper binary is a thin command line wrapper around this library. Other tools can use it
+// to parse saved water detail pages (see parse::parse_page) or to read and write the scraper's database (see db).
+pub mod cache;
+pub mod cli;
+pub mod db;
+pub mod error;
+pub mod export;
+pub mod http;
+pub mod input;
+pub mod models;
+pub mod output;
+pub mod parse;
+pub mod reparse;
+pub mod workers;
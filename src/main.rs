@@ -1,426 +1,722 @@
-use clap::{arg, value_parser, Command, ArgAction};
+use tceq_scraper::{cache, cli, db, export, http, input, output, parse, reparse, workers};
+use tceq_scraper::models::WaterDetail;
+use tceq_scraper::error::{Error, Result};
+use tceq_scraper::parse::PageData;
 
-// Includes necessary sql queries into the shipped exe
-static INSERT_WATER_DETAIL_SQL: &'static str = include_str!("../src/queries/insert_water_detail.sql");
-static INSERT_BUYER_SELLER_RELATIONSHIP_SQL: &'static str = include_str!("../src/queries/insert_buyer_seller_relationship.sql");
+fn main() -> std::process::ExitCode {
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
 
-#[derive(Default, Debug)]
-pub struct BuyerSellerRelationship {
-    pub buyer: String,
-    pub buyer_name: String,
-    pub seller: String,
-    pub population: String,
-    pub availability: String
+fn run() -> Result<()> {
+    // Hidden unless a scrape turns it on
+    let progress_bar: indicatif::ProgressBar = indicatif::ProgressBar::hidden();
+    match cli::parse_args()? {
+        cli::Action::Scrape(config) => {
+            init_logger(config.log_level, progress_bar.clone());
+            scrape(*config, &progress_bar)
+        },
+        cli::Action::Reparse(reparse_config) => {
+            init_logger(reparse_config.log_level, progress_bar);
+            reparse::run(&reparse_config)
+        },
+        cli::Action::Export(export_config) => {
+            init_logger(export_config.log_level, progress_bar);
+            export::run(&export_config)
+        }
+    }
 }
 
-#[derive(Default, Debug, Clone)]
-pub struct WaterDetail {
-    pub is_number: Option<String>,
-    pub st_code: String,
-    pub ws_number: String,
-    pub name: Option<String>
+// Clears the progress bar out of the way while printing each log message, then redraws it below
+struct ProgressLogger {
+    logger: env_logger::Logger,
+    progress_bar: indicatif::ProgressBar
 }
 
-impl WaterDetail {
-    fn url(& self) -> minreq::URL {
-        minreq::URL::from("https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=".to_string() 
-            + &self.is_number.clone().expect("Missing is_number. Cannot build URL.")
-            + "&tinwsys_st_code=" 
-            + &self.st_code 
-            + "&wsnumber=" 
-            + &self.ws_number 
-            + "%20%20%20&DWWState="
-            + &self.st_code)
+impl log::Log for ProgressLogger {
+    fn enabled(& self, metadata: &log::Metadata) -> bool {
+        self.logger.enabled(metadata)
     }
-}
 
-fn main() {
-    unsafe {
-        std::env::set_var("RUST_BACKTRACE", "1");
-    }
-    
-    // Make the default output file name: /current/env/path/[datetime]_out.csv
-    let mut default_output_path: std::ffi::OsString = std::env::current_dir().unwrap().as_os_str().to_owned();
-    let since_epoch: u64 = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-    default_output_path.push("/".to_owned() + since_epoch.to_string().as_str() + "_out.csv");
-    default_output_path = std::path::absolute(default_output_path).unwrap().as_os_str().to_owned();
+    fn log(& self, record: &log::Record) {
+        if self.logger.matches(record) {
+            self.progress_bar.suspend(|| self.logger.log(record));
+        }
+    }
 
-    // Handle arguments
-    let arg_matches = Command::new("tceq-scraper")
-        .version("0.1")
-        .about("Compiles water system data from https://dww2.tceq.texas.gov/ into a csv file.")
-        .arg(
-            arg!(-i <INPUT_CSV>)
-                .value_parser(value_parser!(String))
-                .id("input")
-                .long("input")
-                .required(true)
-                .help("Provide a path to a csv file that contains TCEQ water detail info.")
-                .long_help("CSV should consist of three columns:\n\ttinwsys_is_number\n\ttinwsys_st_code\n\twsnumber\nAll of these values can be found in the URL of the water detail page. (Example: https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=5969&tinwsys_st_code=TX&wsnumber=TX2270001%20%20%20&DWWState=TX)")
-                .action(ArgAction::Set)
-        )
-        .arg(
-            arg!(-o <OUTPUT_CSV>)
-                .value_parser(value_parser!(String))
-                .id("output")
-                .long("output")
-                .required(false)
-                .help("Choose a path to store water data.")
-                .action(ArgAction::Set)
-                .default_value(default_output_path)
-        )
-        .arg(
-            arg!(-d <DELAY>)
-                .value_parser(value_parser!(u32))
-                .id("delay")
-                .long("delay")
-                .required(false)
-                .help("Delay (milliseconds) between website requests.")
-                .long_help("To avoid getting IP blocked for large requests, add a delay between each request to the website.")
-                .action(ArgAction::Set)
-                .default_value("3000")
-        )
-        .arg(
-            arg!(-w <WS_NUMBER_HEADER>)
-                .value_parser(value_parser!(String))
-                .id("header_ws")
-                .long("header_ws")
-                .required(false)
-                .help("Map the \"ws number\" header from the input file.")
-                .long_help("In case the input file's \"ws number\" header does not go by the default name (\"ws_number\"), use this parameter to set a column from the input file as the \"ws number\" column using its header name.") 
-                .action(ArgAction::Set)
-                .default_value("ws_number")
-        )
-        .arg(
-            arg!(-n <IS_NUMBER_HEADER>)
-                .value_parser(value_parser!(String))
-                .id("header_is")
-                .long("header_is")
-                .required(false)
-                .help("Map the \"is number\" header from the input file.")
-                .long_help("In case the input file's \"is number\" header does not go by the default name (\"is_number\"), use this parameter to set a column from the input file as the \"is number\" column using its header name.") 
-                .action(ArgAction::Set)
-                .default_value("is_number")
-        )
-        .arg(
-            arg!(-s <STATE_CODE_HEADER>)
-                .value_parser(value_parser!(String))
-                .id("header_state")
-                .long("header_state")
-                .required(false)
-                .help("Map the \"state code\" header from the input file.")
-                .long_help("In case the input file's \"state code\" header does not go by the default name (\"st_code\"), use this parameter to set a column from the input file as the \"state code\" column using its header name.")
-                .action(ArgAction::Set)
-                .default_value("st_code")
-        )
-        .get_matches();
-    
-    let mut input_file_path: std::path::PathBuf = 
-        std::fs::canonicalize(
-            std::path::Path::new(
-                arg_matches.get_one::<String>("input").expect("input file not provided.")
-            )
-        ).unwrap();
-    let mut output_file_path: std::path::PathBuf = 
-        std::path::absolute(
-            std::path::Path::new(
-                arg_matches.get_one::<String>("output").expect("output file is missing a default value.").as_str()
-            )
-        ).unwrap();
-    
-    // Verify that the input and output files are csv
-    if input_file_path.as_path().extension().is_none() {
-        input_file_path.set_extension(".csv");
-    }
-    else if input_file_path.as_path().extension().is_some_and(|ext| ext != "csv") {
-        panic!("Input file is not a csv.");
+    fn flush(& self) {
+        self.logger.flush();
     }
+}
 
-    if output_file_path.as_path().extension().is_none() {
-        output_file_path.set_extension("csv");
-    }
-    else if output_file_path.as_path().extension().is_some_and(|ext| ext != "csv") {
-        panic!("Output file is not a csv.");
-    } 
-    
-    //println!("input: {} | output: {}", input_file_path.to_str().unwrap(), output_file_path.to_str().unwrap());
+// Sends log messages to stderr, so stdout only ever carries the output csv.
+// RUST_LOG overrides the level set by -v and -q. Other crates only log warnings and errors.
+fn init_logger(log_level: log::LevelFilter, progress_bar: indicatif::ProgressBar) {
+    use std::io::Write;
+    let logger: env_logger::Logger = env_logger::Builder::new()
+        .filter_level(log::LevelFilter::Warn)
+        .filter_module(env!("CARGO_CRATE_NAME"), log_level)
+        .parse_default_env()
+        .format(|buf, record| {
+            match record.level() {
+                log::Level::Error => writeln!(buf, "Error: {}", record.args()),
+                log::Level::Warn => writeln!(buf, "Warning: {}", record.args()),
+                _ => writeln!(buf, "{}", record.args())
+            }
+        })
+        .build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(ProgressLogger { logger, progress_bar })).expect("The logger was already initialized.");
+}
 
-    // Map headers set in arguments to headers from input file
-    println!("Reading headers from input...");
-    let mut reader = csv::Reader::from_path(input_file_path).unwrap();
-    let st_header_arg: &String = &arg_matches.get_one::<String>("header_state").expect("header_state is missing a default value.").to_string();
-    let ws_header_arg: &String= &arg_matches.get_one::<String>("header_ws").expect("header_ws is missing a default value.").to_string();
-    let is_header_arg: &String = &arg_matches.get_one::<String>("header_is").expect("header_is is missing a default value.").to_string();
-    let mut header_map: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
-    for (idx, header) in reader.headers().expect("Header row missing from input file").iter().enumerate() {
-        let h: String = header.to_string();
-        if h == *st_header_arg {
-            header_map.insert(st_header_arg.clone(), idx);
-        }
-        else if h == *ws_header_arg {
-            header_map.insert(ws_header_arg.clone(), idx);
-        }
-        else if h == *is_header_arg {
-            header_map.insert(is_header_arg.clone(), idx);
-        }
-        //println!("{:#?}", header);
-    }
-    
-    // In case there are headers missing from the input,
-    // show the user which headers are missing.
-    if header_map.len() != 3 {
-        let mut missing_headers_list: Vec<String> = Vec::new();
-        if header_map.get(is_header_arg).is_none() {
-            missing_headers_list.push(is_header_arg.clone());
-        }
-        if header_map.get(st_header_arg).is_none() {
-            missing_headers_list.push(st_header_arg.clone());
+fn scrape(config: cli::Config, progress_bar: &indicatif::ProgressBar) -> Result<()> {
+    let run_started: std::time::Instant = std::time::Instant::now();
+    let mut output_writer: Option<output::OutputWriter> = output::open_output_writer(&config)?;
+    let mut manifest: Option<output::Manifest> = config.manifest.as_deref().map(output::Manifest::create).transpose()?;
+    // Set by Ctrl-C. The page being stored is finished, then the run wraps up as if the input had ended, 
+    // so every stored page is committed and the output and manifest are flushed.
+    let interrupted: std::sync::Arc<std::sync::atomic::AtomicBool> = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let handler_interrupted: std::sync::Arc<std::sync::atomic::AtomicBool> = std::sync::Arc::clone(&interrupted);
+    let ctrlc_result = ctrlc::set_handler(move || {
+        // A second Ctrl-C stops right away, in case wrapping up is stuck on a slow request
+        if handler_interrupted.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            std::process::exit(130);
         }
-        if header_map.get(ws_header_arg).is_none() {
-            missing_headers_list.push(ws_header_arg.clone());
-        }
-        let missing_headers: String = 
-            missing_headers_list
-                .iter_mut()
-                .fold("".to_string(), |mut acc, h| {
-                    if acc.len() > 0 {
-                        acc.push_str(", ");
-                    }
-                    acc.push_str(h);
-                    acc
-                });
-        panic!("Missing headers from input file: {}. Double check the header names that were supplied to the -w, -n, and -s arguments.", missing_headers);
+        log::warn!("Stopping after the current page. Press Ctrl-C again to stop right away.");
+    });
+    if let Err(e) = ctrlc_result {
+        log::warn!("Unable to catch Ctrl-C. Stopping the scraper with it may leave the output file incomplete. {}", e);
     }
-    println!("Headers successfully read.");
-
-    // check if the file exists or has any problems opening
-    // if so, print error and abort
-    // otherwise, read the file into memory
-    //let water_details: Vec<WaterDetail> = Vec::new();
-    println!("Reading rows from input...");
-    let mut input_water_details: Vec<WaterDetail> = 
-        reader 
-            .records()
-            .map(|record| {
-                WaterDetail {
-                    is_number: Some(record.as_ref().unwrap().get(*header_map.get(is_header_arg).unwrap()).unwrap().to_string()),
-                    st_code: record.as_ref().unwrap().get(*header_map.get(st_header_arg).unwrap()).unwrap().to_string(),
-                    ws_number: record.as_ref().unwrap().get(*header_map.get(ws_header_arg).unwrap()).unwrap().to_string(),
-                    name: None // Name gets scraped from the page
-                }
-            })
-            .collect();
-    println!("Rows successfully read.");
+    let is_interrupted = || interrupted.load(std::sync::atomic::Ordering::SeqCst);
+    let mut input_rows: input::InputRows = input::read_water_details(&config)?;
 
     // Precompute created timestamp
     let created_timestamp: String = chrono::prelude::Local::now().format("%m-%d-%Y").to_string();
 
     // Get HTML page of each water detail url
-    let delay: u32 = *arg_matches.get_one::<u32>("delay").expect("output file is missing a default value.");
-    println!("Sending requests for each water detail every {} milliseconds...", delay);
-    let whitespace_regex = regex::Regex::new(r"\s+").unwrap();
-    let mut conn = rusqlite::Connection::open("./water_buyer_relationships.db3").unwrap();
-    for (idx, detail) in input_water_details.iter_mut().enumerate() {
-        // Debugging purposes
-        //println!("{:#?}", detail);
-        println!("Scraping water detail {}...", detail.ws_number);
-        let url: minreq::URL = detail.url();
-        match minreq::get(&url).send() {
-            Ok(response) => {
-                if response.status_code < 200 || response.status_code >= 300 {
-                    println!("Failed to extract data because the response status was not OK. CSV Row number: {} | Status code: {} | Reason: {} | Url: {}", idx+1, response.status_code, response.reason_phrase, url)
+    let rate_limiter: Option<std::sync::Arc<http::RateLimiter>> = config.rate.map(|rate| std::sync::Arc::new(http::RateLimiter::per_minute(rate)));
+    // Shared by every pass of workers and by IS number lookups, so the spacing holds across all of them
+    let delay_pacer: std::sync::Arc<http::RateLimiter> = 
+        std::sync::Arc::new(http::RateLimiter::every_with_jitter(
+            std::time::Duration::from_millis(config.request_delay().into()),
+            std::time::Duration::from_millis(config.request_jitter().into())
+        ));
+    if config.request_jitter() > 0 {
+        log::info!("Sending requests for each water detail every {} to {} milliseconds...", config.request_delay(), config.request_delay() + config.request_jitter());
+    }
+    else {
+        log::info!("Sending requests for each water detail every {} milliseconds...", config.request_delay());
+    }
+    match config.proxy.as_deref() {
+        Some(proxy) => log::info!("Sending requests through proxy {}", http::redact_proxy(proxy)),
+        None => log::info!("No proxy configured. Connecting to the website directly.")
+    }
+    let fetcher = http::HttpFetcher { 
+        max_redirects: config.max_redirects, 
+        timeout: config.timeout, 
+        user_agent: config.user_agent.clone(), 
+        proxy: config.proxy.clone() 
+    };
+    let mut conn = 
+        if config.dry_run {
+            log::info!("Dry run: nothing will be written to the database or an output file.");
+            db::open_read_only(&config.db_path)?
+        }
+        else {
+            let conn: rusqlite::Connection = db::open(&config.db_path)?;
+            if config.fast_db {
+                db::enable_fast_writes(&conn)?;
+            }
+            conn
+        };
+    // Water systems scraped by an earlier run. Only looked up for --only-new.
+    let scraped_ws_numbers: std::collections::HashSet<String> = 
+        if config.only_new {
+            db::select_scraped_ws_numbers(&conn)?
+        }
+        else {
+            std::collections::HashSet::new()
+        };
+    let mut already_scraped_skipped: usize = 0;
+    if config.only_new {
+        let input_len: usize = input_rows.water_details.len();
+        input_rows.water_details.retain(|detail| !scraped_ws_numbers.contains(&detail.ws_number));
+        already_scraped_skipped = input_len - input_rows.water_details.len();
+        log::info!("Found {} new water systems. Skipping {} that are already in the database.", input_rows.water_details.len(), already_scraped_skipped);
+    }
+    let input_water_details_len: usize = input_rows.water_details.len();
+    // Includes the lookups of IS numbers
+    let mut requests_sent: u32 = 0;
+    // Every response the website sent this run, including those to retries and IS number lookups
+    let mut response_tally = http::ResponseTally::default();
+    // Input rows with a blank IS number can't be requested until it's found, in the database or on the website's search page
+    let is_number_lookups: usize = input_rows.water_details.iter().filter(|detail| detail.is_number.is_none()).count();
+    let mut unresolved_rows_skipped: usize = 0;
+    if is_number_lookups > 0 {
+        log::info!("Looking up the IS numbers of {} input rows that don't have one...", is_number_lookups);
+        for (idx, detail) in input_rows.water_details.iter_mut().enumerate().filter(|(_, detail)| detail.is_number.is_none()) {
+            let row: usize = detail.source_row.unwrap_or(idx+1);
+            if is_interrupted() {
+                break;
+            }
+            if config.max_requests.is_some_and(|max| requests_sent >= max) {
+                log::warn!("Skipping {} (Row {}) because the request budget ran out before its IS number could be looked up.", detail.ws_number, row);
+                continue;
+            }
+            match find_water_system(&detail.ws_number, &conn, &fetcher, &delay_pacer, rate_limiter.as_deref(), &mut requests_sent, &mut response_tally) {
+                Ok(Some(found)) => detail.is_number = found.is_number,
+                Ok(None) => log::warn!("Skipping {} (Row {}) because its IS number is blank and the website's search page doesn't list it.", detail.ws_number, row),
+                Err(e) => log::warn!("Skipping {} (Row {}) because its IS number is blank and could not be looked up. {}", detail.ws_number, row, e)
+            }
+        }
+        let input_len: usize = input_rows.water_details.len();
+        input_rows.water_details.retain(|detail| detail.is_number.is_some());
+        unresolved_rows_skipped = input_len - input_rows.water_details.len();
+    }
+    // Every row to scrape, in order: the input rows, then the buyers found by --max-depth, one pass per hop
+    let mut crawl_rows: Vec<WaterDetail> = input_rows.water_details.clone();
+    // Water systems queued so far are kept in the database (see db::create_crawl_visited), starting with the input rows
+    if config.max_depth > 0 {
+        db::create_crawl_visited(&conn)?;
+        let tx = conn.transaction()?;
+        for detail in crawl_rows.iter() {
+            db::mark_crawl_visited(&detail.ws_number, &tx)?;
+        }
+        tx.commit()?;
+    }
+    // Buyers found on the pages of the current pass, scraped in the next one
+    let mut discovered_buyers: Vec<String> = Vec::new();
+    let page_cache: Option<cache::PageCache> = config.cache_dir.as_deref().map(|dir| cache::PageCache::open(dir, config.no_cache)).transpose()?;
+    let start_pass = |rows: &[WaterDetail], first_row: usize, requests_sent: u32, conn: &rusqlite::Connection| -> Result<workers::FetchPool> {
+        let queued_rows: Vec<workers::QueuedRow> = queue_rows(rows, first_row, &config, conn)?;
+        // --max-requests covers the whole run, so each pass only gets what the earlier ones left
+        let max_requests: Option<u32> = config.max_requests.map(|max| max.saturating_sub(requests_sent));
+        let fetch_queue: workers::FetchQueue = 
+            workers::FetchQueue::new(queued_rows, fetcher.clone(), rate_limiter.clone(), std::sync::Arc::clone(&delay_pacer), config.request_delay(), config.retries, max_requests)
+                .with_page_cache(page_cache.clone())
+                .with_direction(config.direction);
+        Ok(workers::FetchPool::start(fetch_queue, config.jobs))
+    };
+    let mut fetch_pool: workers::FetchPool = start_pass(&crawl_rows, 0, requests_sent, &conn)?;
+    // The bar stays hidden where it would get in the way: with -q, with --progress-json, or when stderr isn't a terminal
+    if config.log_level >= log::LevelFilter::Info && !config.progress_json && std::io::IsTerminal::is_terminal(&std::io::stderr()) {
+        progress_bar.set_length(crawl_rows.len() as u64);
+        progress_bar.set_style(
+            indicatif::ProgressStyle::with_template("{elapsed_precise} [{wide_bar}] {pos}/{len} rows (ETA {eta})")
+                .expect("The progress bar template is invalid.")
+        );
+        progress_bar.set_draw_target(indicatif::ProgressDrawTarget::stderr());
+    }
+    let mut checked_page_structure: bool = false;
+    let mut population_filtered: usize = 0;
+    let mut system_type_skipped: usize = 0;
+    let mut date_skipped: usize = 0;
+    let mut empty_buyers_skipped: usize = 0;
+    let mut nameless_pages_skipped: usize = 0;
+    let mut row_count_mismatches: usize = 0;
+    let mut pages_rolled_back: usize = 0;
+    let mut resume_skipped: usize = 0;
+    let mut out_of_requests_skipped: usize = 0;
+    let mut pages_from_cache: usize = 0;
+    let mut water_systems_crawled: usize = 0;
+    let mut unresolved_buyers: usize = 0;
+    let mut pages_scraped: usize = 0;
+    // Row numbers of pages that couldn't be fetched or saved, so they can be scraped again
+    let mut failed_rows: Vec<usize> = Vec::new();
+    let mut depth: u32 = 0;
+    let mut pass_start: usize = 0; // Index in crawl_rows of the current pass's first row
+    let mut next_row: usize = 0;
+    // Time spent waiting on the website for water detail pages, not counting the delay between requests
+    let mut request_latency_total: std::time::Duration = std::time::Duration::ZERO;
+    // Requests for water detail pages, including retries. IS number lookups aren't timed, so they're left out of the average latency.
+    let mut water_detail_requests: u32 = 0;
+    let mut water_details_requested: usize = 0;
+    // Set when --fail-fast stops the run. Returned once everything scraped so far is saved and flushed.
+    let mut fail_fast_error: Option<Error> = None;
+    // (buyer, seller) pairs already written this run, so pages that list the same relationship don't write it again
+    let mut stored_relationships: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    // (buyer, seller) pairs from either table of the pages stored (or, with --dry-run, parsed) this run, for the run summary.
+    // Kept apart from stored_relationships, which only tracks the buyers tables, and counted once even when both systems' pages list the pair.
+    let mut found_relationships: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    // Pages are fetched by the workers, but stored here one at a time in input order
+    loop {
+        if is_interrupted() {
+            break;
+        }
+        // Once every row of a pass is stored, the buyers found on its pages make up the next pass
+        if next_row == crawl_rows.len() {
+            if discovered_buyers.is_empty() || out_of_requests_skipped > 0 {
+                break;
+            }
+            let mut new_buyers: Vec<String> = Vec::new();
+            for ws_number in discovered_buyers.drain(..) {
+                if !scraped_ws_numbers.contains(&ws_number) && db::mark_crawl_visited(&ws_number, &conn)? {
+                    new_buyers.push(ws_number);
                 }
-                else {
-                    println!("Parsing URL (Row {})... ({})", idx+1, url);
-                    // Get tecq water data page
-                    let dom = scraper::Html::parse_document(response.as_str().expect("Failed to parse webpage."));
-                    // Fetch the name of this water detail
-                    if detail.name.is_none() {
-                        if let Some(info_table) = get_table_by_name(&"Water System Detail Information".to_string(), &dom) {
-                            detail.name = get_value_from_header(&"Water System Name:".to_string(), &info_table);
-                        }
+            }
+            if new_buyers.is_empty() {
+                break;
+            }
+            requests_sent += fetch_pool.finish();
+            depth += 1;
+            log::info!("Looking up the IS numbers of {} buyers at depth {}...", new_buyers.len(), depth);
+            let mut found_rows: Vec<WaterDetail> = Vec::new();
+            for ws_number in new_buyers.iter() {
+                if is_interrupted() {
+                    break;
+                }
+                if config.max_requests.is_some_and(|max| requests_sent >= max) {
+                    out_of_requests_skipped += 1;
+                    continue;
+                }
+                match find_water_system(ws_number, &conn, &fetcher, &delay_pacer, rate_limiter.as_deref(), &mut requests_sent, &mut response_tally) {
+                    Ok(Some(buyer)) => found_rows.push(buyer),
+                    Ok(None) => {
+                        log::warn!("Skipping buyer {} because the website's search page doesn't list it.", ws_number);
+                        unresolved_buyers += 1;
+                    },
+                    Err(e) => {
+                        log::warn!("Skipping buyer {} because its IS number could not be looked up. {}", ws_number, e);
+                        unresolved_buyers += 1;
                     }
-                    // The key for the hash map is the water detail number string
-                    let mut parsed_water_details: std::collections::HashMap<String, WaterDetail> = std::collections::HashMap::new();
-                    let root_water_detail = WaterDetail {
-                        ws_number: detail.ws_number.clone(),
-                        is_number: detail.is_number.clone(),
-                        st_code: detail.st_code.clone(),
-                        name: detail.name.clone()
-                    };
-                    parsed_water_details.insert(detail.name.clone().unwrap(), root_water_detail.clone());
-                    println!("Adding water detail {} if it doesn't already exist...", detail.ws_number);
-                    let single_wd_tx = conn.transaction().unwrap();
-                    let _ = insert_water_detail(&root_water_detail, &single_wd_tx, &created_timestamp).inspect_err(|e| {
-                        println!("Failed to write water detail {} due to a database error. {}", root_water_detail.ws_number, e);
-                    });
-                    single_wd_tx.commit().expect(format!("Failed to commit the insertion of {}", detail.ws_number).as_str());
-                    println!("Added water detail {}", detail.ws_number);
-                    
-                    if let Some(wbt) = get_table_by_name(&"Buyers of Water".to_string(), &dom) {
-                        let row_selector = scraper::Selector::parse("tbody tr td").expect("Unable to find table rows");
-                        //println!("Found buyers of water table!");
-                        let column_delimiter_regex = regex::Regex::new(r" - |sells to|\/").unwrap();
-                        let rows = 
-                            wbt
-                                .select(&row_selector)
-                                .collect::<Vec<scraper::ElementRef>>();
-                        let mut relationships: Vec<BuyerSellerRelationship> = Vec::new();
-                        //let mut water_details: Vec<WaterDetail> = vec![];
-                        for row in rows {
-                            // Deserialize raw relationship text
-                            // The order of the relationship data is as follows:
-                            // 1. Seller's Water System ID
-                            // 2. Name of Buyer
-                            // 3. Buyer's Water System ID
-                            // 4. Population
-                            // 5. Availability (can be blank)
-                            let mut row_data: Vec<String> = Vec::new();
-                            for txt in row.text().filter(|t| !t.trim().is_empty()) {
-                                let relationship_text = whitespace_regex.replace_all(txt, " ");
-                                if column_delimiter_regex.is_match(&relationship_text) {
-                                    for m in column_delimiter_regex.split(&relationship_text).filter(|res| !res.trim().is_empty()) {
-                                        row_data.push(m.trim().to_string());
-                                    }
-                                }
-                                else {
-                                    row_data.push(relationship_text.trim().to_string());
-                                }
-                            }
-                            if row_data.len() != 0 {
-                                if row_data[0] == "No Buyers" {
-                                    break;
-                                }
-                                while row_data.len() < 5 {
-                                    // In case availability is left blank, we must add 
-                                    // an empty string to row data so that the length is 5.
-                                    row_data.push("".to_string());
-                                }
-                                relationships.push(BuyerSellerRelationship {
-                                    seller: row_data[0].clone(),
-                                    buyer_name: row_data[1].clone(),
-                                    buyer: row_data[2].clone(),
-                                    population: row_data[3].clone(),
-                                    availability: row_data[4].clone()
-                                });
-                            }
-                        }
-                        
-                        println!("Adding all water details found within the 'Buyers of Water' table...");
-                        let wd_tx = conn.transaction().unwrap();
-                        for r in relationships.iter() {
-                            //println!("row: {}", r_idx);
-                            //println!("{:#?}", r);
-                            if parsed_water_details.get(&r.buyer.clone()).is_none() {
-                                let wd = WaterDetail {
-                                    ws_number: r.buyer.clone(),
-                                    st_code: r.buyer[..2].to_string(),
-                                    name: Some(r.buyer_name.clone()),
-                                    is_number: None
-                                };
-                                //println!("{:#?}", wd);
-                                parsed_water_details.insert(wd.ws_number.clone(), wd.clone());
-                                // Insert new water details into database
-                                let _ = insert_water_detail(&wd, &wd_tx, &created_timestamp).inspect_err(|e| {
-                                    println!("Skipped water detail {} due to a database error. {}", wd.ws_number, e);
-                                });
-                            }
+                }
+            }
+            // The next pass still starts, but empty, so that no requests go out after Ctrl-C
+            if is_interrupted() {
+                found_rows.clear();
+            }
+            log::info!("Scraping {} buyers at depth {}...", found_rows.len(), depth);
+            water_systems_crawled += found_rows.len();
+            pass_start = crawl_rows.len();
+            crawl_rows.extend(found_rows);
+            progress_bar.set_length(crawl_rows.len() as u64);
+            fetch_pool = start_pass(&crawl_rows[pass_start..], pass_start, requests_sent, &conn)?;
+            continue;
+        }
+        let idx: usize = next_row;
+        next_row += 1;
+        let detail: &WaterDetail = &crawl_rows[idx];
+        // The row's line in its input file when known, so messages point at the row the user wrote
+        let row: usize = detail.source_row.unwrap_or(idx+1);
+        progress_bar.set_position(idx as u64);
+        let (scrape_result, row_started): (Result<PageData>, std::time::Instant) = 
+            match fetch_pool.take(idx - pass_start) {
+                Some(workers::FetchOutcome::Fetched { result, row_started, latency, responses }) => {
+                    request_latency_total += latency;
+                    water_details_requested += 1;
+                    water_detail_requests += responses.total();
+                    response_tally.add(&responses);
+                    (*result, row_started)
+                },
+                Some(workers::FetchOutcome::Cached(page)) => {
+                    pages_from_cache += 1;
+                    (Ok(*page), std::time::Instant::now())
+                },
+                Some(workers::FetchOutcome::AlreadyScraped) => {
+                    log::info!("Skipping water detail {} because it is already in the database.", detail.ws_number);
+                    resume_skipped += 1;
+                    if config.progress_json {
+                        print_row_progress(row, &detail.ws_number, "already_scraped", std::time::Duration::ZERO);
+                    }
+                    record_manifest_row(manifest.as_mut(), row, detail, None, "already_scraped")?;
+                    continue;
+                },
+                Some(workers::FetchOutcome::OutOfRequests) | None => {
+                    out_of_requests_skipped += 1;
+                    record_manifest_row(manifest.as_mut(), row, detail, None, "out_of_requests")?;
+                    continue;
+                }
+            };
+        let status_code: Option<i32> = 
+            match &scrape_result {
+                Ok(page) => Some(page.status_code),
+                Err(Error::Status { status_code, .. }) => Some(*status_code),
+                Err(Error::NotModified) => Some(304),
+                Err(_) => None
+            };
+        let mut row_progress = |status: &str| -> Result<()> {
+            if config.progress_json {
+                print_row_progress(row, &detail.ws_number, status, row_started.elapsed());
+            }
+            record_manifest_row(manifest.as_mut(), row, detail, status_code, status)
+        };
+        match scrape_result {
+            Ok(mut page) => {
+                // If the first page we get back is missing what the parser looks for, every other page 
+                // likely is too. Stop now rather than spend the whole run collecting nothing.
+                // Either way, whatever was already scraped is saved and flushed before the run ends.
+                let is_first_page: bool = !checked_page_structure;
+                checked_page_structure = true;
+                if is_first_page || config.fail_fast {
+                    if let Err(e) = page.check_structure() {
+                        if is_first_page {
+                            log::error!("Stopping at CSV row {} because the first page scraped is missing what the parser looks for. The website's layout may have changed. Water system: {} | Url: {}", 
+                                row, detail.ws_number, detail.url());
                         }
-                        wd_tx.commit().expect("Failed to commit the insertion of all water details");
-                        println!("Added all water details found within the 'Buyers of Water' table.");
-                        println!("Adding all relationships found within the 'Buyers of Water' table...");
-                        let r_tx = conn.transaction().unwrap();
-                        // Insert new buyer/seller relationships into database
-                        for r in relationships.iter() {
-                            //println!("row: {}", r_idx);
-                            //println!("{:#?}", r);
-                            let _ = insert_buyer_seller_relationship(r, &r_tx, &created_timestamp).inspect_err(|e| {
-                                println!("Skipped relationship '{} sells to {}' due to a database error. {}", r.buyer, r.seller, e);
-                            });
+                        else {
+                            print_fail_fast_row(row, detail);
                         }
-                        r_tx.commit().expect("Failed to commit the insertion of all buyer/seller relationships");
-                        println!("Added all relationships found within the 'Buyers of Water' table.");
-                        println!("Finished scraping {}.", detail.ws_number);
-                        println!("Waiting {} milliseconds before getting next page...", delay);
-                        std::thread::sleep(std::time::Duration::from_millis(delay.into()));
+                        row_progress("filtered")?;
+                        fail_fast_error = Some(e);
+                        break;
                     }
                 }
+                if config.print_parsed {
+                    print_page_data(&page);
+                }
+                // Every stored water system needs a name, so a page without one (i.e., an error page) can't be saved
+                if page.water_detail.name.is_none() {
+                    log::warn!("Skipping {} because no water system name was found on its page. Missing: {}", 
+                        page.water_detail.ws_number, page.missing_page_elements.join(", "));
+                    nameless_pages_skipped += 1;
+                    row_progress("filtered")?;
+                    continue;
+                }
+                if !page.relationship_count_matches() {
+                    log::warn!("The 'Buyers of Water' table of {} has {} data rows, but {} relationships were parsed from it. The parser may be misreading the table.", 
+                        page.water_detail.ws_number, page.buyers_table_rows, page.relationships.len());
+                    row_count_mismatches += 1;
+                }
+                if !page.water_detail.is_updated_between(config.since, config.until) {
+                    log::warn!("Skipping {} because it was last updated on {}, which is outside of --since and --until.", 
+                        page.water_detail.ws_number, page.water_detail.last_updated.as_deref().unwrap_or("an unknown date"));
+                    date_skipped += 1;
+                    row_progress("filtered")?;
+                    continue;
+                }
+                let source_water_category: Option<&str> = page.water_detail.source_water_type.as_deref().and_then(parse::source_water_category);
+                if !config.system_types.is_empty() && !source_water_category.is_some_and(|c| config.system_types.iter().any(|t| t == c)) {
+                    log::warn!("Skipping {} because its primary source water type ({}) is not one of: {}.", 
+                        page.water_detail.ws_number, page.water_detail.source_water_type.as_deref().unwrap_or("unknown"), config.system_types.join(", "));
+                    system_type_skipped += 1;
+                    row_progress("filtered")?;
+                    continue;
+                }
+                if config.direction.includes_buyers() && page.relationships.is_empty() && config.skip_empty_buyers {
+                    log::warn!("Skipping {} because it has no buyers.", page.water_detail.ws_number);
+                    empty_buyers_skipped += 1;
+                    row_progress("filtered")?;
+                    continue;
+                }
+                let mut buyers_scraped: bool = config.direction.includes_buyers() && page.has_buyers_table();
+                if config.direction.includes_buyers() && !buyers_scraped {
+                    log::warn!("No 'Buyers of Water' table was found on the page for {}. Its buyer count is left unknown.", page.water_detail.ws_number);
+                }
+                if config.active_only && page.water_detail.is_inactive() {
+                    log::warn!("Skipping the 'Buyers of Water' and 'Sellers of Water' tables of {} because it is inactive.", page.water_detail.ws_number);
+                    page.relationships.clear();
+                    page.seller_relationships.clear();
+                    buyers_scraped = false;
+                }
+                if let Some(population_min) = config.population_min {
+                    let relationships_len: usize = page.relationships.len();
+                    page.relationships.retain(|r| parse::parse_population(&r.population).is_none_or(|population| population >= population_min));
+                    population_filtered += relationships_len - page.relationships.len();
+                }
+                if depth < config.max_depth {
+                    discovered_buyers.extend(page.relationships.iter().map(|r| r.buyer.clone()));
+                }
+                if config.dry_run {
+                    print_dry_run_page(&page);
+                    found_relationships.extend(page_relationship_keys(&page));
+                    pages_scraped += 1;
+                    row_progress("scraped")?;
+                    continue;
+                }
+                match db::store_page(&page, buyers_scraped, config.upsert, &mut conn, &created_timestamp, &mut stored_relationships) {
+                    Ok(()) => (),
+                    Err(Error::Database(e)) => {
+                        log::error!("Failed to store water detail {} due to a database error. Nothing from its page was saved. CSV Row number: {} | Error: {}", page.water_detail.ws_number, row, e);
+                        pages_rolled_back += 1;
+                        failed_rows.push(row);
+                        row_progress("database_error")?;
+                        if config.fail_fast {
+                            print_fail_fast_row(row, detail);
+                            fail_fast_error = Some(Error::Database(e));
+                            break;
+                        }
+                        continue;
+                    },
+                    Err(e) => return Err(e)
+                }
+                found_relationships.extend(page_relationship_keys(&page));
+                if let Some(writer) = output_writer.as_mut() {
+                    output::write_page(writer, &page, &config.output_fields)?;
+                }
+                if config.store_html {
+                    db::store_page_html(&page.water_detail.ws_number, &page.html, &conn, &created_timestamp)?;
+                }
+                log::info!("Finished scraping {}.", page.water_detail.ws_number);
+                pages_scraped += 1;
+                row_progress("scraped")?;
+            },
+            Err(Error::NotModified) => {
+                log::info!("Skipped water detail {} because it has not changed since it was last scraped.", detail.ws_number);
+                row_progress("not_modified")?;
+            },
+            Err(Error::Status { status_code, reason_phrase, retry_after }) => {
+                log::error!("Failed to extract data because the response status was not OK. CSV Row number: {} | Status code: {} | Reason: {} | Url: {}", row, status_code, reason_phrase, detail.url());
+                failed_rows.push(row);
+                row_progress("status_error")?;
+                if config.fail_fast {
+                    print_fail_fast_row(row, detail);
+                    fail_fast_error = Some(Error::Status { status_code, reason_phrase, retry_after });
+                    break;
+                }
+            },
+            Err(Error::Request(e)) => {
+                if http::is_timeout(&e) {
+                    log::error!("Failed to extract data because the request timed out after {} seconds. CSV Row number: {} | Url: {}", config.timeout, row, detail.url());
+                }
+                else {
+                    log::error!("Failed to extract data because the request was unsuccessful. CSV Row number: {} | Error: {} | Url: {}", row, e, detail.url());
+                }
+                failed_rows.push(row);
+                row_progress("request_error")?;
+                if config.fail_fast {
+                    print_fail_fast_row(row, detail);
+                    fail_fast_error = Some(Error::Request(e));
+                    break;
+                }
             },
-            Err(e) => println!("Failed to extract data because the request was unsuccessful. CSV Row number: {} | Error: {}", idx+1, e)
+            Err(e) => return Err(e)
         }
     }
-    conn.close().expect("Failed to close connection to water_buyer_relationships database");
+    progress_bar.finish_and_clear();
+    requests_sent += fetch_pool.finish();
+    if is_interrupted() {
+        log::warn!("Stopped by Ctrl-C. Skipped the remaining {} rows.", crawl_rows.len() - next_row);
+    }
+    if out_of_requests_skipped > 0 {
+        log::warn!("Request budget exhausted after {} requests. Skipped the remaining {} rows.", requests_sent, out_of_requests_skipped);
+    }
+    if !config.dry_run {
+        log::info!("Updating buyer and seller counts...");
+        let degree_counts_updated: usize = db::update_degree_counts(&conn)?;
+        log::info!("Updated buyer and seller counts of {} water details.", degree_counts_updated);
+    }
+    let average_latency: std::time::Duration = request_latency_total.checked_div(water_detail_requests).unwrap_or_default();
+    log::info!("Run summary:");
+    if config.dry_run {
+        log::info!("\tParsed {}/{} pages, {} relationships, {} failures (dry run, nothing was stored)", pages_scraped, crawl_rows.len(), found_relationships.len(), failed_rows.len());
+    }
+    else {
+        log::info!("\tScraped {}/{} pages, {} relationships, {} failures", pages_scraped, crawl_rows.len(), found_relationships.len(), failed_rows.len());
+    }
+    if !failed_rows.is_empty() {
+        log::info!("\tFailed rows: {}", failed_rows.iter().map(|row| row.to_string()).collect::<Vec<String>>().join(", "));
+    }
+    log::info!("\tCompleted {} water details ({} requests) in {}, avg {:.2}s/request", water_details_requested, requests_sent, format_duration(run_started.elapsed()), average_latency.as_secs_f64());
+    // Printed even with -q, since it's what shows whether the delay is low enough to get blocked
+    if log::log_enabled!(log::Level::Info) {
+        log::info!("\tResponses: {}", response_tally);
+    }
+    else {
+        eprintln!("Responses: {}", response_tally);
+    }
+    log::info!("\tBlank rows skipped: {}", input_rows.blank_rows_skipped);
+    log::info!("\tDuplicate rows skipped: {}", input_rows.duplicate_rows_skipped);
+    log::info!("\tMalformed rows skipped: {}", input_rows.malformed_rows_skipped);
+    if config.cache_dir.is_some() {
+        log::info!("\tPages loaded from the cache: {}", pages_from_cache);
+    }
+    if !config.states.is_empty() {
+        log::info!("\tRows from other states skipped: {}", input_rows.other_state_rows_skipped);
+    }
+    if config.limit.is_some() {
+        log::info!("\tRows past the limit skipped: {}", input_rows.limited_rows_skipped);
+    }
+    if config.sample.is_some() {
+        log::info!("\tRows left out of the sample: {}", input_rows.unsampled_rows_skipped);
+    }
+    if config.resume {
+        log::info!("\tWater systems already scraped before resuming: {}", resume_skipped);
+    }
+    if config.only_new {
+        log::info!("\tNew water systems: {}", input_water_details_len);
+        log::info!("\tWater systems already in the database: {}", already_scraped_skipped);
+    }
+    if is_number_lookups > 0 {
+        log::info!("\tRows with a blank IS number: {} ({} skipped because it wasn't found)", is_number_lookups, unresolved_rows_skipped);
+    }
+    if config.max_depth > 0 {
+        log::info!("\tWater systems found by following buyers: {}", water_systems_crawled);
+        log::info!("\tBuyers skipped because their IS number wasn't found: {}", unresolved_buyers);
+    }
+    log::info!("\tRelationships below the population minimum: {}", population_filtered);
+    log::info!("\tWater systems skipped by source water type: {}", system_type_skipped);
+    if config.since.is_some() || config.until.is_some() {
+        log::info!("\tWater systems skipped by last updated date: {}", date_skipped);
+    }
+    if config.skip_empty_buyers {
+        log::info!("\tWater systems skipped for having no buyers: {}", empty_buyers_skipped);
+    }
+    log::info!("\tPages skipped for having no water system name: {}", nameless_pages_skipped);
+    log::info!("\tBuyers tables with a relationship count mismatch: {}", row_count_mismatches);
+    log::info!("\tPages not saved due to a database error: {}", pages_rolled_back);
+    conn.close().map_err(|(_, e)| Error::Database(e))?;
+    if let Some(writer) = output_writer {
+        output::finish(writer)?;
+    }
+    match fail_fast_error {
+        Some(e) => Err(e),
+        None if is_interrupted() => Err(Error::Interrupted),
+        None => Ok(())
+    }
 }
 
-fn get_table_by_name<'a>(name: &'a String, dom: &'a scraper::Html) -> Option<scraper::ElementRef<'a>> {
-    let table_selector = scraper::Selector::parse("body table tbody tr td table").expect("Unable to find a table within the webpage.");
-    return dom
-            .select(&table_selector)
-            .filter(|el| {
-                //if let Some(header) = el.select(table_header_selector)
-                let mut text_iter = el.text().filter(|t| !t.trim().is_empty());
-                if let Some(first_header_text) = text_iter.next() {
-                    let txt = first_header_text.trim();
-                    return txt == name
+// Looks up everything the workers need from the database for a pass of rows, since only the main thread uses the connection
+fn queue_rows(rows: &[WaterDetail], first_row: usize, config: &cli::Config, conn: &rusqlite::Connection) -> Result<Vec<workers::QueuedRow>> {
+    rows
+        .iter()
+        .enumerate()
+        .map(|(idx, detail)| {
+            let already_scraped: bool = config.resume && db::water_detail_exists(&detail.ws_number, conn)?;
+            let validators: Option<http::PageValidators> = 
+                if config.no_cache || config.dry_run || already_scraped {
+                    None
                 }
-                return false
-            })
-            .collect::<Vec<scraper::ElementRef>>()
-            .first()
-            .copied()
+                else {
+                    db::select_page_validators(&detail.ws_number, conn).unwrap_or_else(|e| {
+                        log::warn!("Failed to look up cached page validators for {} due to a database error. {}", detail.ws_number, e);
+                        None
+                    })
+                };
+            Ok(workers::QueuedRow { row: detail.source_row.unwrap_or(first_row + idx + 1), detail: detail.clone(), already_scraped, validators })
+        })
+        .collect()
 }
 
-// Finds a header (the key), then returns the value
-// NOTE: if the header in TCEQ includes a colon (i.e., "Water System Name:"), 
-// then header_name needs that colon too.
-fn get_value_from_header(header_name: &String, table: &scraper::ElementRef) -> Option<String> {
-    let whitespace_regex = regex::Regex::new(r"\s+").unwrap();
-    let cell_header_text_selector = scraper::Selector::parse("tbody tr td").expect("Unable to find header text");
-    let mut found_header: bool = false;
-    let cells = table.select(&cell_header_text_selector);
-    for cell in cells {
-        for raw_txt in cell.text().filter(|t| !t.trim().is_empty()) {
-            let txt = whitespace_regex.replace_all(raw_txt.trim(), " ");
-            if found_header {
-                return Some(txt.to_string())
-            }
-            else if txt == *header_name {
-                // We store the value of the next sibling cell as the name
-                found_header = true;
-            }
-        }
+// Builds the water detail of a system known only by its number (i.e., a buyer found by --max-depth). Its IS number comes
+// from the database when an earlier run stored it, or else from the website's search page, which counts as a request.
+fn find_water_system(
+    ws_number: &str, 
+    conn: &rusqlite::Connection, 
+    fetcher: &http::HttpFetcher, 
+    delay_pacer: &http::RateLimiter, 
+    rate_limiter: Option<&http::RateLimiter>, 
+    requests_sent: &mut u32,
+    response_tally: &mut http::ResponseTally
+) -> Result<Option<WaterDetail>> {
+    if let Some(stored) = db::select_water_detail(&ws_number.to_string(), conn)?.filter(|detail| detail.is_number.is_some()) {
+        // The name is read from the page
+        return Ok(Some(WaterDetail { name: None, ..stored }))
+    }
+    delay_pacer.acquire();
+    if let Some(limiter) = rate_limiter {
+        limiter.acquire();
     }
-    return None
+    *requests_sent += 1;
+    let st_code: String = parse::state_code_of(ws_number);
+    // The lookup only succeeds on a 2xx response
+    let is_number: Option<String> = 
+        parse::resolve_is_number(ws_number, &st_code, fetcher)
+            .inspect(|_| response_tally.record_status(200))
+            .inspect_err(|e| response_tally.record_error(e))?;
+    Ok(is_number.map(|is_number| WaterDetail {
+        is_number: Some(is_number),
+        st_code,
+        ws_number: ws_number.to_string(),
+        ..Default::default()
+    }))
 }
 
-fn insert_water_detail(
-    water_detail: &WaterDetail, 
-    tx: &rusqlite::Transaction, 
-    created_timestamp: &String
-) -> Result<i64, rusqlite::Error> {
-    let mut stmt = tx.prepare(INSERT_WATER_DETAIL_SQL).unwrap();
-    let result = stmt.insert(rusqlite::named_params! {
-        ":water_system_no": water_detail.ws_number,
-        ":water_system_name": water_detail.name,
-        ":state_code": water_detail.st_code,
-        ":is_no": water_detail.is_number,
-        ":created_timestamp": created_timestamp
-    });
-    return result
+// Shows which input row stopped a --fail-fast run
+fn print_fail_fast_row(row: usize, detail: &WaterDetail) {
+    log::error!("Stopping at CSV row {} because --fail-fast is set. Water system: {} | State code: {} | IS number: {} | Url: {}", 
+        row, detail.ws_number, detail.st_code, detail.is_number.as_deref().unwrap_or(""), detail.url());
 }
 
-fn insert_buyer_seller_relationship(
-    relationship: &BuyerSellerRelationship,
-    tx: &rusqlite::Transaction, 
-    created_timestamp: &String
-) -> Result<i64, rusqlite::Error> {
-    let mut stmt = tx.prepare(INSERT_BUYER_SELLER_RELATIONSHIP_SQL).unwrap();
-    let result = stmt.insert(rusqlite::named_params! {
-        ":seller": relationship.seller,
-        ":buyer": relationship.buyer,
-        ":population": relationship.population,
-        ":availability": relationship.availability,
-        ":created_timestamp": created_timestamp
-    });
-    return result
+// Formats a duration for the run summary (i.e., "3h12m", "4m5s", or "2.98s")
+fn format_duration(duration: std::time::Duration) -> String {
+    let total_secs: u64 = duration.as_secs();
+    let (hours, minutes, seconds) = (total_secs / 3600, (total_secs % 3600) / 60, total_secs % 60);
+    if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    }
+    else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    }
+    else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+// Adds a processed row to the --manifest, if there is one, grouping its status into scraped, skipped, or failed
+fn record_manifest_row(manifest: Option<&mut output::Manifest>, row: usize, detail: &WaterDetail, status_code: Option<i32>, status: &str) -> Result<()> {
+    let Some(manifest) = manifest else {
+        return Ok(())
+    };
+    let outcome: &str = 
+        match status {
+            "scraped" => "scraped",
+            "status_error" | "request_error" | "database_error" => "failed",
+            _ => "skipped"
+        };
+    manifest.write_row(&output::ManifestRow { source_row: row, ws_number: &detail.ws_number, url: &detail.url(), status_code, outcome })
+}
+
+// One line of --progress-json output
+#[derive(serde::Serialize)]
+struct RowProgress<'a> {
+    row: usize,
+    ws_number: &'a str,
+    status: &'a str, // "scraped", "filtered", "not_modified", "status_error", "request_error", "database_error", or "already_scraped". Keep --progress-json's help in sync.
+    elapsed_ms: u128 // Time spent on the row, not counting the delay before the next one
+}
+
+// Writes a row's outcome to stderr as a single JSON line, regardless of where progress messages go
+fn print_row_progress(row: usize, ws_number: &str, status: &str, elapsed: std::time::Duration) {
+    let line: String = 
+        serde_json::to_string(&RowProgress { row, ws_number, status, elapsed_ms: elapsed.as_millis() })
+            .expect("Failed to serialize row progress");
+    eprintln!("{}", line);
+}
+
+// The (buyer, seller) pair of every relationship on the page, from both the buyers and the sellers table
+fn page_relationship_keys(page: &PageData) -> impl Iterator<Item = (String, String)> + '_ {
+    page.relationships.iter()
+        .chain(page.seller_relationships.iter())
+        .map(|r| (r.buyer.clone(), r.seller.clone()))
+}
+
+// What --dry-run would have stored from a page: the page's own water detail, plus one for each buyer and seller
+fn print_dry_run_page(page: &PageData) {
+    log::info!("Dry run: would store {} water details and {} relationships from {} ({} buyers, {} sellers).", 
+        1 + page.relationships.len() + page.seller_relationships.len(), 
+        page.relationships.len() + page.seller_relationships.len(), 
+        page.water_detail.ws_number, 
+        page.relationships.len(), 
+        page.seller_relationships.len());
+}
+
+// Prints everything parsed from a page as a single block so it stands apart from the surrounding progress messages
+fn print_page_data(page: &PageData) {
+    let detail: &WaterDetail = &page.water_detail;
+    let mut block: String = format!("Parsed water detail {}:\n", detail.ws_number);
+    block.push_str(&format!("\tName: {}\n", detail.name.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tState code: {}\n", detail.st_code));
+    block.push_str(&format!("\tIS number: {}\n", detail.is_number.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tActivity status: {}\n", detail.activity_status.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tPrimary source water type: {}\n", detail.source_water_type.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tFederal primary source: {}\n", detail.federal_source.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tCounty: {}\n", detail.county.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tAddress: {}\n", detail.address.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tCity: {}\n", detail.city.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tZip code: {}\n", detail.zip.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tLast updated: {}\n", detail.last_updated.as_deref().unwrap_or("")));
+    block.push_str(&format!("\tBuyers ({}):", page.relationships.len()));
+    for r in page.relationships.iter() {
+        block.push_str(&format!("\n\t\t{} sells to {} ({}) | Population: {} | Availability: {}", r.seller, r.buyer_name, r.buyer, r.population, r.availability));
+    }
+    block.push_str(&format!("\n\tSellers ({}):", page.seller_relationships.len()));
+    for r in page.seller_relationships.iter() {
+        block.push_str(&format!("\n\t\t{} ({}) sells to {} | Population: {} | Availability: {}", r.seller_name, r.seller, r.buyer, r.population, r.availability));
+    }
+    log::info!("{}", block);
 }
@@ -0,0 +1,69 @@
+// Field names are part of the JSON format, so renaming a field is a breaking change
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BuyerSellerRelationship {
+    pub buyer: String, // Buyer's water system number
+    pub buyer_name: String,
+    pub seller: String, // Seller's water system number
+    pub seller_name: String,
+    pub population: String, // Population cell as shown on the page (i.e., "1,234*"). See parse_population for the number.
+    pub availability: String // Availability code (i.e., "P" for permanent). Can be blank.
+}
+
+// Field names are part of the JSON format, so renaming a field is a breaking change
+#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WaterDetail {
+    pub is_number: Option<String>, // tinwsys_is_number from the water detail URL
+    pub st_code: String, // Two letter state code
+    pub ws_number: String, // Water system number (i.e., "TX2270001")
+    pub name: Option<String>,
+    pub activity_status: Option<String>,
+    pub source_water_type: Option<String>, // Primary source water type as shown on the page (i.e., "Surface Water")
+    pub county: Option<String>, // Principal county served (i.e., "TRAVIS")
+    pub federal_source: Option<String>, // Federal primary source code (i.e., "SW" for surface water)
+    pub address: Option<String>, // Street address as shown on the page, without the city or zip code
+    pub city: Option<String>,
+    pub zip: Option<String>, // Kept as text since some zip codes have the +4 suffix (i.e., "78701-1234")
+    pub last_updated: Option<String>, // When the page says its data was last updated, as YYYY-MM-DD. See parse::parse_page_date.
+    // Line of the input file this water system was read from, for messages about it.
+    // None for rows of a JSON array and for buyers found by --max-depth. Never stored or written out.
+    #[serde(skip)]
+    pub source_row: Option<usize>
+}
+
+// A single scraped record of either kind, tagged by "record_type" when serialized
+// (i.e., {"record_type":"relationship","buyer":"TX1050176",...}), for JSON that mixes both kinds in one stream.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "record_type", rename_all = "snake_case")]
+pub enum Record {
+    WaterDetail(WaterDetail),
+    Relationship(BuyerSellerRelationship)
+}
+
+impl WaterDetail {
+    pub fn url(& self) -> minreq::URL {
+        minreq::URL::from("https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=".to_string()
+            + &self.is_number.clone().expect("Missing is_number. Cannot build URL.")
+            + "&tinwsys_st_code="
+            + &self.st_code
+            + "&wsnumber="
+            + &crate::parse::normalize_ws_number(&self.ws_number)
+            + "%20%20%20&DWWState="
+            + &self.st_code)
+    }
+
+    // Whether the system's last updated date is within --since and --until (both inclusive).
+    // Systems whose page doesn't give a date are always kept, since there is nothing to compare.
+    pub fn is_updated_between(& self, since: Option<chrono::NaiveDate>, until: Option<chrono::NaiveDate>) -> bool {
+        let Some(last_updated) = self.last_updated.as_deref().and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()) else {
+            return true
+        };
+        since.is_none_or(|since| last_updated >= since) && until.is_none_or(|until| last_updated <= until)
+    }
+
+    // Systems with an unknown activity status are treated as active
+    pub fn is_inactive(& self) -> bool {
+        self.activity_status
+            .as_deref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("Inactive") || status.eq_ignore_ascii_case("I"))
+    }
+}
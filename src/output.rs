@@ -0,0 +1,327 @@
+use std::io::Write;
+
+use crate::cli::{Config, ExportConfig, OutputTarget};
+use crate::error::{Error, Result};
+use crate::models::{BuyerSellerRelationship, WaterDetail};
+use crate::parse::{parse_population, PageData};
+
+static UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+pub enum OutputWriter {
+    Csv(Box<csv::Writer<Box<dyn std::io::Write>>>),
+    // Written as a JSON array, one element (i.e., a water system) per line, so it can be streamed as pages are scraped
+    Json {
+        writer: Box<dyn std::io::Write>,
+        elements_written: usize
+    }
+}
+
+// Every column of the output csv, in order, for --fields. Must match the fields of OutputRow.
+pub static OUTPUT_FIELDS: &[&str] = &[
+    "is_number", "st_code", "ws_number", "name", "activity_status", "source_water_type", "county", "federal_source",
+    "address", "city", "zip", "last_updated", "seller", "buyer", "buyer_name", "population", "population_raw", "availability"
+];
+
+// Fields of each buyer and seller in the JSON output, and the --fields names that select them
+static JSON_RELATIONSHIP_FIELDS: &[(&str, &[&str])] = &[
+    ("buyer", &["buyer"]),
+    ("buyer_name", &["buyer_name"]),
+    ("seller", &["seller"]),
+    ("seller_name", &["seller"]),
+    ("population", &["population", "population_raw"]),
+    ("availability", &["availability"])
+];
+
+// One line of the output csv: a scraped water detail, paired with one of its buyers.
+// Field names are the header row, so renaming a field changes the output format.
+#[derive(serde::Serialize)]
+struct OutputRow<'a> {
+    is_number: Option<&'a str>,
+    st_code: &'a str,
+    ws_number: &'a str,
+    name: Option<&'a str>,
+    activity_status: Option<&'a str>,
+    source_water_type: Option<&'a str>,
+    county: Option<&'a str>,
+    federal_source: Option<&'a str>,
+    address: Option<&'a str>,
+    city: Option<&'a str>,
+    zip: Option<&'a str>,
+    last_updated: Option<&'a str>,
+    // Blank when the water detail has no buyers
+    seller: Option<&'a str>,
+    buyer: Option<&'a str>,
+    buyer_name: Option<&'a str>,
+    population: Option<u64>,
+    population_raw: Option<&'a str>,
+    availability: Option<&'a str>
+}
+
+impl<'a> OutputRow<'a> {
+    fn new(detail: &'a WaterDetail, relationship: Option<&'a BuyerSellerRelationship>) -> OutputRow<'a> {
+        OutputRow {
+            is_number: detail.is_number.as_deref(),
+            st_code: &detail.st_code,
+            ws_number: &detail.ws_number,
+            name: detail.name.as_deref(),
+            activity_status: detail.activity_status.as_deref(),
+            source_water_type: detail.source_water_type.as_deref(),
+            county: detail.county.as_deref(),
+            federal_source: detail.federal_source.as_deref(),
+            address: detail.address.as_deref(),
+            city: detail.city.as_deref(),
+            zip: detail.zip.as_deref(),
+            last_updated: detail.last_updated.as_deref(),
+            seller: relationship.map(|r| r.seller.as_str()),
+            buyer: relationship.map(|r| r.buyer.as_str()),
+            buyer_name: relationship.map(|r| r.buyer_name.as_str()),
+            population: relationship.and_then(|r| parse_population(&r.population)),
+            population_raw: relationship.map(|r| r.population.as_str()),
+            availability: relationship.map(|r| r.availability.as_str())
+        }
+    }
+}
+
+// One element of the JSON output: a scraped water detail along with every buyer and seller found on its page
+#[derive(serde::Serialize)]
+struct JsonWaterSystem<'a> {
+    #[serde(flatten)]
+    water_detail: &'a WaterDetail,
+    buyers: &'a [BuyerSellerRelationship],
+    sellers: &'a [BuyerSellerRelationship]
+}
+
+// Only the --fields columns of a record, in the order they were given
+struct SelectedFields<'a, T: serde::Serialize> {
+    record: &'a T,
+    fields: &'a [&'static str]
+}
+
+impl<T: serde::Serialize> serde::Serialize for SelectedFields<'_, T> {
+    fn serialize<S: serde::Serializer>(& self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let value: serde_json::Value = serde_json::to_value(self.record).map_err(serde::ser::Error::custom)?;
+        let mut selected = serializer.serialize_struct("SelectedFields", self.fields.len())?;
+        for field in self.fields {
+            selected.serialize_field(field, &value[*field])?;
+        }
+        selected.end()
+    }
+}
+
+// Opens the output file (or stdout) as a csv or JSON writer using the output settings. Returns None when output is disabled.
+// The file is opened before anything is scraped, so a path that can't be written to fails the run right away.
+pub fn open_output_writer(config: &Config) -> Result<Option<OutputWriter>> {
+    if let OutputTarget::File(path) = &config.output {
+        create_parent_dir(path)?;
+    }
+    // Appending to a file that already has rows leaves its header row (and BOM) alone
+    let mut is_continuing_file: bool = false;
+    let mut output_file: Box<dyn std::io::Write> =
+        match &config.output {
+            OutputTarget::File(path) if config.is_append_output => {
+                let output_file_error = |source| Error::OutputFile { path: path.clone(), source };
+                is_continuing_file = path.metadata().is_ok_and(|m| m.len() > 0);
+                if is_continuing_file && !has_output_header(path, config.output_delimiter, &config.output_fields).map_err(output_file_error)? {
+                    log::warn!("{} doesn't start with the scraper's header row. New rows are appended to it anyway, without a header.", path.display());
+                }
+                Box::new(
+                    std::fs::OpenOptions::new()
+                        .append(true)
+                        .create(true)
+                        .open(path)
+                        .map_err(output_file_error)?
+                )
+            },
+            OutputTarget::File(path) => Box::new(
+                std::fs::File::create(path)
+                    .map_err(|source| Error::OutputFile { path: path.clone(), source })?
+            ),
+            OutputTarget::Stdout => Box::new(std::io::stdout()),
+            OutputTarget::Disabled => return Ok(None)
+        };
+    if config.is_json_output {
+        output_file.write_all(b"[")?;
+        return Ok(Some(OutputWriter::Json { writer: output_file, elements_written: 0 }))
+    }
+    if config.output_bom && !is_continuing_file {
+        output_file.write_all(UTF8_BOM)?;
+    }
+    Ok(Some(OutputWriter::Csv(Box::new(csv::WriterBuilder::new()
+        .delimiter(config.output_delimiter)
+        .quote_style(config.output_quote_style)
+        .has_headers(!is_continuing_file)
+        .from_writer(output_file)))))
+}
+
+// Opens the export file (or stdout) as a csv or JSON writer. An existing file is replaced.
+pub fn open_export_writer(config: &ExportConfig) -> Result<OutputWriter> {
+    if let OutputTarget::File(path) = &config.output {
+        create_parent_dir(path)?;
+    }
+    let mut output_file: Box<dyn std::io::Write> =
+        match &config.output {
+            OutputTarget::File(path) => Box::new(
+                std::fs::File::create(path)
+                    .map_err(|source| Error::OutputFile { path: path.clone(), source })?
+            ),
+            OutputTarget::Stdout | OutputTarget::Disabled => Box::new(std::io::stdout())
+        };
+    if config.is_json_output {
+        output_file.write_all(b"[")?;
+        return Ok(OutputWriter::Json { writer: output_file, elements_written: 0 })
+    }
+    Ok(OutputWriter::Csv(Box::new(csv::WriterBuilder::new()
+        .delimiter(config.output_delimiter)
+        .from_writer(output_file))))
+}
+
+// Creates any missing directories leading up to an output file
+fn create_parent_dir(path: &std::path::Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => 
+            std::fs::create_dir_all(parent).map_err(|source| Error::OutputFile { path: path.to_path_buf(), source }),
+        _ => Ok(())
+    }
+}
+
+// Audit trail of every row the scraper processed (see --manifest), kept apart from the scraped data.
+// Each line is flushed as soon as it's written, so a run that crashes still leaves a manifest of everything up to that point.
+pub struct Manifest {
+    writer: csv::Writer<std::fs::File>
+}
+
+// One line of the manifest. Field names are the header row.
+#[derive(serde::Serialize)]
+pub struct ManifestRow<'a> {
+    pub source_row: usize,
+    pub ws_number: &'a str,
+    pub url: &'a str,
+    pub status_code: Option<i32>, // Blank when no response came back (i.e., a timeout) or no request was sent
+    pub outcome: &'a str // "scraped", "skipped", or "failed"
+}
+
+impl Manifest {
+    // An existing file is replaced
+    pub fn create(path: &std::path::Path) -> Result<Manifest> {
+        create_parent_dir(path)?;
+        let file: std::fs::File = std::fs::File::create(path).map_err(|source| Error::OutputFile { path: path.to_path_buf(), source })?;
+        Ok(Manifest { writer: csv::Writer::from_writer(file) })
+    }
+
+    pub fn write_row(&mut self, row: &ManifestRow) -> Result<()> {
+        self.writer.serialize(row).map_err(std::io::Error::from)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// Checks whether the first row of an existing csv is the header row that write_page writes. A leading BOM is ignored.
+fn has_output_header(path: &std::path::Path, delimiter: u8, fields: &[&'static str]) -> std::io::Result<bool> {
+    let mut header_writer = csv::Writer::from_writer(Vec::new());
+    let empty_detail: WaterDetail = WaterDetail::default();
+    let empty_row: OutputRow = OutputRow::new(&empty_detail, None);
+    if fields.is_empty() {
+        header_writer.serialize(empty_row).map_err(std::io::Error::from)?;
+    }
+    else {
+        header_writer.serialize(SelectedFields { record: &empty_row, fields }).map_err(std::io::Error::from)?;
+    }
+    let header_csv: Vec<u8> = header_writer.into_inner().map_err(|e| e.into_error())?;
+    let expected_header: csv::StringRecord = csv::Reader::from_reader(header_csv.as_slice()).headers().map_err(std::io::Error::from)?.clone();
+    let mut existing_reader = 
+        csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)
+            .map_err(std::io::Error::from)?;
+    let mut first_row = csv::StringRecord::new();
+    existing_reader.read_record(&mut first_row).map_err(std::io::Error::from)?;
+    Ok(first_row == expected_header)
+}
+
+// For csv, writes one row per relationship of the page, or a single row with blank relationship columns if it has none.
+// The header row is written along with the first row.
+// For JSON, writes the page as one element of the array.
+// Only the given fields (see --fields) are written, or every field if there are none.
+pub fn write_page(writer: &mut OutputWriter, page: &PageData, fields: &[&'static str]) -> Result<()> {
+    let detail: &WaterDetail = &page.water_detail;
+    if matches!(writer, OutputWriter::Json { .. }) {
+        let water_system = JsonWaterSystem { water_detail: detail, buyers: &page.relationships, sellers: &page.seller_relationships };
+        if fields.is_empty() {
+            return write_record(writer, &water_system)
+        }
+        return write_record(writer, &select_json_fields(&water_system, fields)?)
+    }
+    let rows: Vec<OutputRow> = 
+        if page.relationships.is_empty() {
+            vec![OutputRow::new(detail, None)]
+        }
+        else {
+            page.relationships.iter().map(|r| OutputRow::new(detail, Some(r))).collect()
+        };
+    for row in rows.iter() {
+        if fields.is_empty() {
+            write_record(writer, row)?;
+        }
+        else {
+            write_record(writer, &SelectedFields { record: row, fields })?;
+        }
+    }
+    Ok(())
+}
+
+// Keeps the selected water detail fields of a JSON water system, and the selected fields of each of its buyers and sellers.
+// The buyers and sellers lists are left out if none of their fields are selected.
+fn select_json_fields(water_system: &JsonWaterSystem, fields: &[&'static str]) -> Result<serde_json::Value> {
+    let mut value: serde_json::Value = serde_json::to_value(water_system).map_err(std::io::Error::from)?;
+    let relationship_fields: Vec<&str> = 
+        JSON_RELATIONSHIP_FIELDS
+            .iter()
+            .filter(|(_, selected_by)| selected_by.iter().any(|field| fields.contains(field)))
+            .map(|(name, _)| *name)
+            .collect();
+    if let serde_json::Value::Object(object) = &mut value {
+        object.retain(|key, list| {
+            if key != "buyers" && key != "sellers" {
+                return fields.contains(&key.as_str())
+            }
+            if let serde_json::Value::Array(relationships) = list {
+                for relationship in relationships.iter_mut().filter_map(|r| r.as_object_mut()) {
+                    relationship.retain(|field, _| relationship_fields.contains(&field.as_str()));
+                }
+            }
+            !relationship_fields.is_empty()
+        });
+    }
+    Ok(value)
+}
+
+// Writes a record as one csv row, or as one element of the JSON array. For csv, the header row comes from
+// the record's field names and is written along with the first row.
+pub fn write_record<T: serde::Serialize>(writer: &mut OutputWriter, record: &T) -> Result<()> {
+    match writer {
+        OutputWriter::Csv(writer) => writer.serialize(record).map_err(std::io::Error::from)?,
+        OutputWriter::Json { writer, elements_written } => {
+            let separator: &[u8] = if *elements_written == 0 { b"\n" } else { b",\n" };
+            writer.write_all(separator)?;
+            serde_json::to_writer(&mut *writer, record).map_err(std::io::Error::from)?;
+            *elements_written += 1;
+        }
+    }
+    Ok(())
+}
+
+// Closes the JSON array, if there is one, and flushes everything written so far
+pub fn finish(writer: OutputWriter) -> Result<()> {
+    match writer {
+        OutputWriter::Csv(mut writer) => writer.flush()?,
+        OutputWriter::Json { mut writer, elements_written } => {
+            let closing: &[u8] = if elements_written == 0 { b"]\n" } else { b"\n]\n" };
+            writer.write_all(closing)?;
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
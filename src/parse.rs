@@ -0,0 +1,542 @@
+use crate::error::{Error, Result};
+use crate::http::{FetchResponse, Fetcher, PageValidators};
+use crate::models::{BuyerSellerRelationship, WaterDetail};
+
+// Labels the parser relies on to find data within a water detail page
+static WATER_SYSTEM_DETAIL_TABLE: &str = "Water System Detail Information";
+static BUYERS_OF_WATER_TABLE: &str = "Buyers of Water";
+static SELLERS_OF_WATER_TABLE: &str = "Sellers of Water";
+// Labels the water system name has gone by, tried in order. The first is the current label.
+static WATER_SYSTEM_NAME_HEADERS: &[&str] = &["Water System Name:", "System Name:", "PWS Name:"];
+static SOURCE_WATER_TYPE_HEADER: &str = "Primary Source Water Type:";
+// Optional fields, so their labels are tried in order but never reported as missing
+static COUNTY_HEADERS: &[&str] = &["Principal County Served:", "County Served:", "County:"];
+static FEDERAL_SOURCE_HEADERS: &[&str] = &["Federal Primary Source:", "Federal Source:"];
+static ADDRESS_HEADERS: &[&str] = &["Physical Address:", "Address:", "Mailing Address:"];
+static CITY_HEADERS: &[&str] = &["City:"];
+static ZIP_HEADERS: &[&str] = &["Zip Code:", "Zip:"];
+static LAST_UPDATED_HEADERS: &[&str] = &["Last Updated:", "Last Update Date:", "Data As Of:", "As Of Date:"];
+// Date formats the last updated date has been seen in, tried in order
+static PAGE_DATE_FORMATS: &[&str] = &["%m/%d/%Y", "%m-%d-%Y", "%Y-%m-%d", "%d-%b-%y", "%d-%b-%Y"];
+
+// Search page of the TCEQ website. Lists every water system whose number starts with the given text.
+static WATER_SYSTEM_SEARCH_URL: &str = "https://dww2.tceq.texas.gov/DWW/JSP/SearchDispatch";
+// Page every water detail link points to
+static WATER_SYSTEM_DETAIL_PAGE: &str = "WaterSystemDetail.jsp";
+
+// Categories accepted by --system-type
+pub static SOURCE_WATER_CATEGORIES: &[&str] = &["groundwater", "surface", "purchased"];
+
+// Which relationship tables of a detail page are read (see --direction). The other table is skipped as if
+// it weren't on the page, except that it isn't reported as missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    Buyers, // Only the "Buyers of Water" table
+    Sellers, // Only the "Sellers of Water" table
+    #[default]
+    Both
+}
+
+impl Direction {
+    pub fn includes_buyers(self) -> bool {
+        self != Direction::Sellers
+    }
+
+    pub fn includes_sellers(self) -> bool {
+        self != Direction::Buyers
+    }
+}
+
+// Everything scraped from a single water detail page
+#[derive(Debug)]
+pub struct PageData {
+    pub water_detail: WaterDetail, // The requested water detail, enriched with the name and activity status from the page
+    pub relationships: Vec<BuyerSellerRelationship>, // Rows of the "Buyers of Water" table
+    pub seller_relationships: Vec<BuyerSellerRelationship>, // Rows of the "Sellers of Water" table, with this system as the buyer
+    pub buyers_table_rows: usize, // Data rows in the "Buyers of Water" table. Should match the number of relationships.
+    pub status_code: i32,
+    pub missing_page_elements: Vec<&'static str>, // Expected tables and headers that could not be found on the page
+    pub validators: PageValidators, // Sent back on the next request for this page to skip it if unchanged
+    pub html: String // The raw page, kept for --store-html
+}
+
+impl PageData {
+    // Also true when the buyers table was skipped by --direction, so check the direction too before relying on the buyers
+    pub fn has_buyers_table(& self) -> bool {
+        !self.missing_page_elements.contains(&BUYERS_OF_WATER_TABLE)
+    }
+
+    // Each data row of the buyers table should become exactly one relationship.
+    // A mismatch means the parser is misreading the table (i.e., a row split across several cells).
+    pub fn relationship_count_matches(& self) -> bool {
+        self.relationships.len() == self.buyers_table_rows
+    }
+
+    // Fails if the page is missing the detail table or the water system name, without which nothing from it can be stored.
+    // A missing 'Buyers of Water' table is allowed, since the page's buyers are then just unknown (see has_buyers_table).
+    pub fn check_structure(& self) -> Result<()> {
+        let missing: Vec<&str> = 
+            self.missing_page_elements
+                .iter()
+                .copied()
+                .filter(|element| *element != BUYERS_OF_WATER_TABLE)
+                .collect();
+        if missing.is_empty() {
+            return Ok(())
+        }
+        Err(Error::PageStructure {
+            missing: missing.join(", "),
+            ws_number: self.water_detail.ws_number.clone(),
+            url: self.water_detail.url().to_string()
+        })
+    }
+}
+
+// Fetches a single water detail page, turning a 304 or any other non-2xx response into an error. Does not parse,
+// write anything, or wait between requests; those are left to the caller, so it can time the request on its own.
+pub fn fetch_detail(detail: &WaterDetail, fetcher: &dyn Fetcher, validators: Option<&PageValidators>) -> Result<FetchResponse> {
+    let response: FetchResponse = fetcher.fetch(&detail.url(), validators)?;
+    if response.status_code == 304 {
+        return Err(Error::NotModified)
+    }
+    if response.status_code < 200 || response.status_code >= 300 {
+        return Err(Error::Status { status_code: response.status_code, reason_phrase: response.reason_phrase, retry_after: response.retry_after })
+    }
+    Ok(response)
+}
+
+// Looks up the IS number of a water system on the website's search page. Systems only found in another system's
+// buyers or sellers table are stored without one, and their page can't be requested until it's known.
+// Returns None if the search doesn't list the system.
+pub fn resolve_is_number(ws_number: &str, st_code: &str, fetcher: &dyn Fetcher) -> Result<Option<String>> {
+    let ws_number: String = normalize_ws_number(ws_number);
+    let search_url: String = format!(
+        "{}?number={}&name=&county=All&WaterSystemType=All&SourceWaterType=All&PointOfContactType=None&SampleType=null&begin_date=&end_date=&action=Search+For+Water+Systems",
+        WATER_SYSTEM_SEARCH_URL, ws_number);
+    let response: FetchResponse = fetcher.fetch(&search_url, None)?;
+    if response.status_code < 200 || response.status_code >= 300 {
+        return Err(Error::Status { status_code: response.status_code, reason_phrase: response.reason_phrase, retry_after: response.retry_after })
+    }
+    let dom = scraper::Html::parse_document(&response.body);
+    let link_selector = scraper::Selector::parse("a[href]").expect("Unable to find links");
+    // The search matches by prefix, so the results may list other systems too
+    Ok(dom
+        .select(&link_selector)
+        .filter_map(|link| link.value().attr("href").and_then(water_detail_from_url))
+        .find(|detail| detail.ws_number == ws_number && detail.st_code.eq_ignore_ascii_case(st_code))
+        .and_then(|detail| detail.is_number))
+}
+
+// Reads the IS number, state code, and water system number out of a water detail page URL, i.e.
+// https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=5969&tinwsys_st_code=TX&wsnumber=TX2270192%20%20%20&DWWState=TX
+// Relative links to the page work too. Returns None for any other URL, or if one of the three values is missing.
+pub fn water_detail_from_url(url: &str) -> Option<WaterDetail> {
+    let url: &str = url.trim().split('#').next().unwrap_or_default();
+    let (page, query) = url.split_once('?')?;
+    if !page.ends_with(WATER_SYSTEM_DETAIL_PAGE) {
+        return None
+    }
+    let query_value = |name: &str| -> Option<String> {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| decode_query_value(value).trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    Some(WaterDetail {
+        is_number: Some(query_value("tinwsys_is_number")?),
+        st_code: query_value("tinwsys_st_code")?.to_uppercase(),
+        ws_number: normalize_ws_number(&query_value("wsnumber")?),
+        ..Default::default()
+    })
+}
+
+// Undoes the percent-encoding of a URL query value (i.e., "TX2270192%20%20%20" becomes "TX2270192   ")
+fn decode_query_value(value: &str) -> String {
+    let bytes: &[u8] = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut idx: usize = 0;
+    while idx < bytes.len() {
+        let escaped: Option<u8> = 
+            value
+                .get(idx+1..idx+3)
+                .filter(|_| bytes[idx] == b'%')
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[idx], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                idx += 3;
+            },
+            (b'+', None) => {
+                decoded.push(b' ');
+                idx += 1;
+            },
+            (byte, None) => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// Parses a water detail page that has already been fetched (or loaded from a stored copy).
+// Only the relationship tables of the given direction are read.
+pub fn parse_page(detail: &WaterDetail, response: FetchResponse, direction: Direction) -> PageData {
+    // Get tecq water data page
+    let dom = scraper::Html::parse_document(&response.body);
+    let mut water_detail: WaterDetail = detail.clone();
+    let mut missing_page_elements: Vec<&'static str> = Vec::new();
+    // Fetch the name and activity status of this water detail
+    match get_table_by_name(WATER_SYSTEM_DETAIL_TABLE, &dom) {
+        Some(info_table) => {
+            let page_name: Option<String> = 
+                WATER_SYSTEM_NAME_HEADERS
+                    .iter()
+                    .find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            if page_name.is_none() {
+                missing_page_elements.push(WATER_SYSTEM_NAME_HEADERS[0]);
+            }
+            if water_detail.name.is_none() {
+                water_detail.name = page_name;
+            }
+            water_detail.activity_status = get_value_from_header(&"Activity Status:".to_string(), &info_table);
+            water_detail.source_water_type = get_value_from_header(&SOURCE_WATER_TYPE_HEADER.to_string(), &info_table);
+            water_detail.county = COUNTY_HEADERS.iter().find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            water_detail.federal_source = FEDERAL_SOURCE_HEADERS.iter().find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            water_detail.address = ADDRESS_HEADERS.iter().find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            water_detail.city = CITY_HEADERS.iter().find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            water_detail.zip = ZIP_HEADERS.iter().find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            let last_updated: Option<String> = LAST_UPDATED_HEADERS.iter().find_map(|header| get_value_from_header(&header.to_string(), &info_table));
+            water_detail.last_updated = last_updated.as_deref().and_then(|text| {
+                let date: Option<chrono::NaiveDate> = parse_page_date(text);
+                if date.is_none() {
+                    log::warn!("The last updated date of {} (\"{}\") is not in a known format. It is left blank.", water_detail.ws_number, text);
+                }
+                date.map(|date| date.format("%Y-%m-%d").to_string())
+            });
+        },
+        None => {
+            // i.e., an error page, or a layout change. The page is still stored under its water system number.
+            log::warn!("No '{}' table was found on the page for {}. Its name and details are left blank.", WATER_SYSTEM_DETAIL_TABLE, water_detail.ws_number);
+            missing_page_elements.push(WATER_SYSTEM_DETAIL_TABLE)
+        }
+    }
+    let (mut relationships, buyers_table_rows): (Vec<BuyerSellerRelationship>, usize) = 
+        match get_table_by_name(BUYERS_OF_WATER_TABLE, &dom) {
+            _ if !direction.includes_buyers() => (Vec::new(), 0),
+            Some(wbt) => parse_buyers_table(&wbt),
+            None => {
+                missing_page_elements.push(BUYERS_OF_WATER_TABLE);
+                (Vec::new(), 0)
+            }
+        };
+    // The sellers table is optional, so a page without one is not reported as missing anything
+    let mut seller_relationships: Vec<BuyerSellerRelationship> = 
+        get_table_by_name(SELLERS_OF_WATER_TABLE, &dom)
+            .filter(|_| direction.includes_sellers())
+            .map(|wst| parse_sellers_table(&wst))
+            .unwrap_or_default();
+    let page_name: String = water_detail.name.clone().unwrap_or_default();
+    for r in relationships.iter_mut() {
+        r.seller_name = page_name.clone();
+    }
+    for r in seller_relationships.iter_mut() {
+        r.buyer = water_detail.ws_number.clone();
+        r.buyer_name = page_name.clone();
+    }
+    PageData {
+        water_detail,
+        relationships,
+        seller_relationships,
+        buyers_table_rows,
+        status_code: response.status_code,
+        missing_page_elements,
+        validators: response.validators,
+        html: response.body
+    }
+}
+
+// Returns the relationships along with the number of data rows they were read from.
+// The seller's name is left blank for the caller to fill in, since it's the name of the page's own system.
+pub fn parse_buyers_table(wbt: &scraper::ElementRef) -> (Vec<BuyerSellerRelationship>, usize) {
+    let (rows, data_rows): (Vec<Vec<String>>, usize) = split_relationship_rows(wbt, BUYERS_OF_WATER_TABLE, "No Buyers");
+    // The order of the relationship data is as follows:
+    // 1. Seller's Water System ID
+    // 2. Name of Buyer
+    // 3. Buyer's Water System ID
+    // 4. Population
+    // 5. Availability (can be blank)
+    let relationships: Vec<BuyerSellerRelationship> = 
+        rows
+            .into_iter()
+            .map(|row_data| BuyerSellerRelationship {
+                seller: normalize_ws_number(&row_data[0]),
+                seller_name: String::new(),
+                buyer_name: row_data[1].clone(),
+                buyer: normalize_ws_number(&row_data[2]),
+                population: row_data[3].clone(),
+                availability: row_data[4].clone()
+            })
+            .collect();
+    (relationships, data_rows)
+}
+
+// Reads the "Sellers of Water" table, whose rows are laid out like those of the buyers table but name the seller instead.
+// The buyer is always the page's own system, so it is left blank for the caller to fill in.
+// The order of the relationship data is as follows:
+// 1. Seller's Water System ID
+// 2. Name of Seller
+// 3. Buyer's Water System ID (the page's own system)
+// 4. Population
+// 5. Availability (can be blank)
+pub fn parse_sellers_table(wst: &scraper::ElementRef) -> Vec<BuyerSellerRelationship> {
+    let (rows, _): (Vec<Vec<String>>, usize) = split_relationship_rows(wst, SELLERS_OF_WATER_TABLE, "No Sellers");
+    rows
+        .into_iter()
+        .map(|row_data| BuyerSellerRelationship {
+            seller: normalize_ws_number(&row_data[0]),
+            seller_name: row_data[1].clone(),
+            buyer_name: String::new(),
+            buyer: String::new(),
+            population: row_data[3].clone(),
+            availability: row_data[4].clone()
+        })
+        .collect()
+}
+
+// Splits every relationship in a buyers or sellers table into its five fields.
+// Rows with a cell per field are read cell by cell. Otherwise each cell holds a whole relationship, which is split
+// by split_relationship_cell.
+// Returns the split relationships along with the number of data rows they were read from.
+fn split_relationship_rows(table: &scraper::ElementRef, table_name: &str, no_rows_text: &str) -> (Vec<Vec<String>>, usize) {
+    let whitespace_regex = regex::Regex::new(r"\s+").unwrap();
+    let relationship_regex = regex::Regex::new(RELATIONSHIP_CELL_PATTERN).unwrap();
+    let column_delimiter_regex = regex::Regex::new(r" - |sells to|\/").unwrap();
+    let row_selector = scraper::Selector::parse("tbody tr").expect("Unable to find table rows");
+    let header_cell_selector = scraper::Selector::parse("th").expect("Unable to find header cells");
+    let cell_selector = scraper::Selector::parse("td").expect("Unable to find table cells");
+    let mut relationships: Vec<Vec<String>> = Vec::new();
+    let mut data_rows: usize = 0;
+    'rows: for row in table.select(&row_selector) {
+        // Header rows are sometimes rendered inside the tbody instead of the thead
+        if row.select(&header_cell_selector).next().is_some() {
+            continue;
+        }
+        let cells: Vec<scraper::ElementRef> = row.select(&cell_selector).collect();
+        let cell_rows: Vec<Vec<String>> = 
+            if cells.len() >= 4 {
+                // The availability cell may be missing, and is padded below
+                vec![cells
+                    .iter()
+                    .map(|cell| whitespace_regex.replace_all(&cell.text().collect::<Vec<&str>>().join(" "), " ").trim().to_string())
+                    .collect()]
+            }
+            else {
+                cells
+                    .iter()
+                    .map(|cell| split_relationship_cell(cell, &whitespace_regex, &relationship_regex, &column_delimiter_regex))
+                    .collect()
+            };
+        let mut is_data_row: bool = false;
+        for mut row_data in cell_rows {
+            if row_data.iter().all(|field| field.is_empty()) {
+                continue;
+            }
+            if row_data[0] == no_rows_text {
+                break 'rows;
+            }
+            if is_table_label(&row_data, table_name) {
+                continue;
+            }
+            if is_total_row(&row_data) {
+                log::debug!("Skipping the total row \"{}\" of the '{}' table.", row_data.join(" / "), table_name);
+                continue;
+            }
+            while row_data.len() < 5 {
+                // In case availability is left blank, we must add 
+                // an empty string to row data so that the length is 5.
+                row_data.push("".to_string());
+            }
+            relationships.push(row_data);
+            is_data_row = true;
+        }
+        if is_data_row {
+            data_rows += 1;
+        }
+    }
+    (relationships, data_rows)
+}
+
+// A whole relationship in one cell (i.e., "TX2270192 - CITY OF X - Y WATER / TX1050176 - 1,234 / P"). The name is
+// everything between the seller's number and the "/ [buyer's number] -" that follows it, so names with a hyphen
+// or slash of their own are kept whole. The availability (and the slash before it) can be left out.
+static RELATIONSHIP_CELL_PATTERN: &str = r"^([A-Za-z]{2}\d+) ?(?:-|sells to) ?(.*?) ?/ ?([A-Za-z]{2}\d+) ?- ?([^/]*?)(?: ?/ ?(.*?))?$";
+
+// Splits a cell holding a whole relationship into its fields. Cells that don't look like one (i.e., column labels,
+// "No Buyers", or a total row) are split on every " - ", "sells to", and "/" instead, one piece of text at a time.
+fn split_relationship_cell(
+    cell: &scraper::ElementRef, 
+    whitespace_regex: &regex::Regex, 
+    relationship_regex: &regex::Regex, 
+    column_delimiter_regex: &regex::Regex
+) -> Vec<String> {
+    let texts: Vec<String> = 
+        cell.text()
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| whitespace_regex.replace_all(t, " ").trim().to_string())
+            .collect();
+    if let Some(captures) = relationship_regex.captures(&texts.join(" ")) {
+        return (1..=5)
+            .map(|idx| captures.get(idx).map(|m| m.as_str().trim().to_string()).unwrap_or_default())
+            .collect()
+    }
+    let mut row_data: Vec<String> = Vec::new();
+    for relationship_text in texts.iter() {
+        if column_delimiter_regex.is_match(relationship_text) {
+            for m in column_delimiter_regex.split(relationship_text).filter(|res| !res.trim().is_empty()) {
+                row_data.push(m.trim().to_string());
+            }
+        }
+        else {
+            row_data.push(relationship_text.to_string());
+        }
+    }
+    row_data
+}
+
+// Sorts a primary source water type into one of SOURCE_WATER_CATEGORIES (i.e., "Purchased Surface Water" is "purchased").
+// Purchased water is its own category regardless of where the seller gets it from.
+pub fn source_water_category(source_water_type: &str) -> Option<&'static str> {
+    let source_water_type: String = source_water_type.to_lowercase();
+    if source_water_type.contains("purchased") {
+        Some("purchased")
+    }
+    else if source_water_type.contains("surface") {
+        Some("surface")
+    }
+    else if source_water_type.contains("ground") {
+        Some("groundwater")
+    }
+    else {
+        None
+    }
+}
+
+// Parses a date as shown on a detail page (i.e., "03/15/2024" or "15-MAR-24"). Anything after the date, like a time, is ignored.
+// Returns None if the date isn't in one of PAGE_DATE_FORMATS.
+pub fn parse_page_date(text: &str) -> Option<chrono::NaiveDate> {
+    let date: &str = text.split_whitespace().next()?;
+    PAGE_DATE_FORMATS
+        .iter()
+        .find_map(|format| chrono::NaiveDate::parse_from_str(date, format).ok())
+}
+
+// Parses a population cell into a number, ignoring thousands separators and whitespace (i.e., " 1,234 " becomes 1234).
+// Anything after the number, like a footnote marker or the upper end of a range, is ignored (i.e., "1,234*" becomes 1234
+// and "100-200" becomes 100), so keep the cell text too when that matters.
+// Returns None for blank cells and cells that don't start with a number.
+pub fn parse_population(population: &str) -> Option<u64> {
+    population
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .take_while(|c| c.is_ascii_digit() || *c == ',')
+        .filter(|c| *c != ',')
+        .collect::<String>()
+        .parse::<u64>()
+        .ok()
+}
+
+// Column labels that show up in header rows of the "Buyers of Water" and "Sellers of Water" tables
+static RELATIONSHIP_TABLE_COLUMN_LABELS: &[&str] = &["seller", "buyer", "buyer name", "seller name", "population", "availability", "water system", "water system no.", "pws id"];
+
+// Whether a parsed cell is the table's title or column labels rather than a relationship.
+// Catches header rows that use td cells, which the th check in split_relationship_rows can't.
+fn is_table_label(row_data: &[String], table_name: &str) -> bool {
+    if row_data[0].eq_ignore_ascii_case(table_name) {
+        return true
+    }
+    row_data
+        .iter()
+        .all(|field| RELATIONSHIP_TABLE_COLUMN_LABELS.contains(&field.trim_end_matches(':').to_lowercase().as_str()))
+}
+
+// Whether a parsed cell is a summary row (i.e., "Total Population: 1,284") rather than a relationship.
+// Only rows starting with "Total" are treated as one. Other short rows are kept, so that a table the parser
+// misreads still shows up as a relationship count mismatch instead of quietly losing rows.
+fn is_total_row(row_data: &[String]) -> bool {
+    row_data[0].get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case("total"))
+}
+
+// Puts a water system number into its canonical form: trimmed and uppercase (i.e., " tx2270192   " becomes "TX2270192").
+// Apply this before a water system number is used as a map key, stored, or put into a URL so the same system is never
+// recorded under two different keys.
+pub fn normalize_ws_number(ws_number: &str) -> String {
+    ws_number.trim().to_uppercase()
+}
+
+// The two letter state code that starts a water system number (i.e., "TX" for "TX2270192").
+// Shorter or unusual numbers give whatever is there, possibly nothing, instead of panicking.
+pub fn state_code_of(ws_number: &str) -> String {
+    ws_number.chars().take(2).collect()
+}
+
+// Finds the nested table whose first line of text is the given name (i.e., "Buyers of Water").
+// Runs of whitespace, a trailing colon, and case are ignored on both sides (i.e., "BUYERS  OF WATER:" matches).
+pub fn get_table_by_name<'a>(name: &str, dom: &'a scraper::Html) -> Option<scraper::ElementRef<'a>> {
+    let table_selector = scraper::Selector::parse("body table tbody tr td table").expect("Unable to find a table within the webpage.");
+    let whitespace_regex = regex::Regex::new(r"\s+").unwrap();
+    let normalize_header = |txt: &str| -> String {
+        whitespace_regex.replace_all(txt.trim(), " ").trim_end_matches(':').trim_end().to_lowercase()
+    };
+    let name: String = normalize_header(name);
+    dom
+            .select(&table_selector)
+            .filter(|el| {
+                let mut text_iter = el.text().filter(|t| !t.trim().is_empty());
+                if let Some(first_header_text) = text_iter.next() {
+                    return normalize_header(first_header_text) == name
+                }
+                false
+            })
+            .collect::<Vec<scraper::ElementRef>>()
+            .first()
+            .copied()
+}
+
+// Finds a header (the key), then returns the value
+// NOTE: if the header in TCEQ includes a colon (i.e., "Water System Name:"), 
+// then header_name needs that colon too.
+pub fn get_value_from_header(header_name: &String, table: &scraper::ElementRef) -> Option<String> {
+    let whitespace_regex = regex::Regex::new(r"\s+").unwrap();
+    let cell_header_text_selector = scraper::Selector::parse("tbody tr td").expect("Unable to find header text");
+    let mut found_header: bool = false;
+    let cells = table.select(&cell_header_text_selector);
+    for cell in cells {
+        for raw_txt in cell.text().filter(|t| !t.trim().is_empty()) {
+            let txt = whitespace_regex.replace_all(raw_txt.trim(), " ");
+            if found_header {
+                return Some(txt.to_string())
+            }
+            else if txt == *header_name {
+                // We store the value of the next sibling cell as the name
+                found_header = true;
+            }
+            else if let Some(value) = value_in_header_cell(&txt, header_name) {
+                // Some layouts put the header and its value in one cell (i.e., "Water System Name: CITY OF TEST")
+                return Some(value)
+            }
+        }
+    }
+    None
+}
+
+// Splits "Label: Value" text on its first colon and returns the value if the label matches header_name
+fn value_in_header_cell(txt: &str, header_name: &str) -> Option<String> {
+    let (label, value) = txt.split_once(':')?;
+    if label.trim() != header_name.trim_end_matches(':').trim() || value.trim().is_empty() {
+        return None
+    }
+    Some(value.trim().to_string())
+}
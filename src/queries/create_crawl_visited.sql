@@ -0,0 +1,3 @@
+create temp table if not exists crawl_visited (
+    water_system_no TEXT PRIMARY KEY NOT NULL
+);
@@ -0,0 +1,59 @@
+create table if not exists water_systems (
+    water_system_no TEXT UNIQUE ON CONFLICT ROLLBACK NOT NULL ON CONFLICT ROLLBACK PRIMARY KEY ASC ON CONFLICT ROLLBACK,
+    name TEXT NOT NULL ON CONFLICT ROLLBACK,
+    state_code TEXT (2) NOT NULL,
+    is_no TEXT,
+    created TEXT NOT NULL ON CONFLICT FAIL,
+    activity_status TEXT,
+    buyer_count INTEGER,
+    seller_count INTEGER,
+    source_water_type TEXT,
+    buyers_scraped INTEGER,
+    county TEXT,
+    federal_source TEXT,
+    address TEXT,
+    city TEXT,
+    zip TEXT,
+    last_updated TEXT,
+    scraped_at TEXT
+);
+
+create unique index if not exists water_system_no_idx on water_systems (water_system_no ASC);
+
+create table if not exists availability_codes (
+    id INTEGER PRIMARY KEY ON CONFLICT ROLLBACK AUTOINCREMENT UNIQUE ON CONFLICT ROLLBACK NOT NULL ON CONFLICT ROLLBACK,
+    code TEXT UNIQUE ON CONFLICT ROLLBACK NOT NULL ON CONFLICT ROLLBACK,
+    "full" TEXT NOT NULL ON CONFLICT ROLLBACK UNIQUE ON CONFLICT ROLLBACK
+);
+
+insert or ignore into availability_codes (id, code, "full")
+values
+    (1, 'S', 'Seasonal'),
+    (2, 'E', 'Emergency'),
+    (3, 'I', 'Interim'),
+    (4, 'P', 'Permanent'),
+    (5, 'O', 'Other');
+
+create table if not exists water_buyer_relationships (
+    seller TEXT REFERENCES water_systems (water_system_no) NOT NULL,
+    buyer TEXT REFERENCES water_systems (water_system_no) NOT NULL ON CONFLICT ROLLBACK,
+    population INTEGER,
+    availability INTEGER REFERENCES availability_codes (id) ON UPDATE CASCADE,
+    created TEXT NOT NULL ON CONFLICT FAIL,
+    population_raw TEXT NOT NULL ON CONFLICT ROLLBACK,
+    scraped_at TEXT,
+    PRIMARY KEY (buyer, seller)
+);
+
+create table if not exists page_validators (
+    water_system_no TEXT PRIMARY KEY NOT NULL REFERENCES water_systems (water_system_no),
+    etag TEXT,
+    last_modified TEXT,
+    created TEXT NOT NULL
+);
+
+create table if not exists page_html (
+    water_system_no TEXT PRIMARY KEY NOT NULL REFERENCES water_systems (water_system_no),
+    html BLOB NOT NULL,
+    created TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+delete from water_buyer_relationships
+where seller = :seller;
@@ -2,13 +2,17 @@ insert or replace into water_buyer_relationships (
     seller,
     buyer,
     population,
+    population_raw,
     availability,
-    created
+    created,
+    scraped_at
 )
 values (
     :seller,
     :buyer,
     :population,
+    :population_raw,
     (select id from availability_codes where code = :availability),
-    :created_timestamp
+    :created_timestamp,
+    :scraped_at
 );
@@ -0,0 +1,18 @@
+insert or ignore into water_buyer_relationships (
+    seller,
+    buyer,
+    population,
+    population_raw,
+    availability,
+    created,
+    scraped_at
+)
+values (
+    :seller,
+    :buyer,
+    :population,
+    :population_raw,
+    (select id from availability_codes where code = :availability),
+    :created_timestamp,
+    :scraped_at
+);
@@ -0,0 +1,2 @@
+insert or ignore into crawl_visited (water_system_no)
+values (:water_system_no);
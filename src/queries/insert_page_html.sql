@@ -0,0 +1,10 @@
+insert or replace into page_html (
+    water_system_no,
+    html,
+    created
+)
+values (
+    :water_system_no,
+    :html,
+    :created_timestamp
+);
@@ -0,0 +1,12 @@
+insert or replace into page_validators (
+    water_system_no,
+    etag,
+    last_modified,
+    created
+)
+values (
+    :water_system_no,
+    :etag,
+    :last_modified,
+    :created_timestamp
+);
@@ -3,13 +3,31 @@ insert or replace into water_systems (
     name, 
     state_code, 
     is_no,
-    created
+    activity_status,
+    source_water_type,
+    county,
+    federal_source,
+    address,
+    city,
+    zip,
+    last_updated,
+    created,
+    scraped_at
 )
 values (
     :water_system_no, 
     :water_system_name, 
     :state_code, 
 	:is_no,
-    :created_timestamp
+    :activity_status,
+    :source_water_type,
+    :county,
+    :federal_source,
+    :address,
+    :city,
+    :zip,
+    :last_updated,
+    :created_timestamp,
+    :scraped_at
 );
    
@@ -0,0 +1,33 @@
+insert or ignore into water_systems (
+    water_system_no, 
+    name, 
+    state_code, 
+    is_no,
+    activity_status,
+    source_water_type,
+    county,
+    federal_source,
+    address,
+    city,
+    zip,
+    last_updated,
+    created,
+    scraped_at
+)
+values (
+    :water_system_no, 
+    :water_system_name, 
+    :state_code, 
+	:is_no,
+    :activity_status,
+    :source_water_type,
+    :county,
+    :federal_source,
+    :address,
+    :city,
+    :zip,
+    :last_updated,
+    :created_timestamp,
+    :scraped_at
+);
+   
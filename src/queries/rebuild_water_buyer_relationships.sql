@@ -0,0 +1,18 @@
+create table water_buyer_relationships_rebuilt (
+    seller TEXT REFERENCES water_systems (water_system_no) NOT NULL,
+    buyer TEXT REFERENCES water_systems (water_system_no) NOT NULL ON CONFLICT ROLLBACK,
+    population INTEGER,
+    availability INTEGER REFERENCES availability_codes (id) ON UPDATE CASCADE,
+    created TEXT NOT NULL ON CONFLICT FAIL,
+    population_raw TEXT NOT NULL ON CONFLICT ROLLBACK,
+    scraped_at TEXT,
+    PRIMARY KEY (buyer, seller)
+);
+
+insert into water_buyer_relationships_rebuilt (seller, buyer, population, availability, created, population_raw, scraped_at)
+select seller, buyer, population, availability, created, coalesce(population_raw, cast(population as TEXT), ''), scraped_at
+from water_buyer_relationships;
+
+drop table water_buyer_relationships;
+
+alter table water_buyer_relationships_rebuilt rename to water_buyer_relationships;
@@ -0,0 +1,5 @@
+select
+    water_system_no,
+    html
+from page_html
+order by water_system_no;
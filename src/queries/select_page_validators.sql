@@ -0,0 +1,5 @@
+select
+    etag,
+    last_modified
+from page_validators
+where water_system_no = :water_system_no;
@@ -0,0 +1,12 @@
+select
+    r.seller,
+    s.name,
+    r.buyer,
+    w.name,
+    r.population_raw,
+    a.code
+from water_buyer_relationships r
+left join water_systems s on s.water_system_no = r.seller
+left join water_systems w on w.water_system_no = r.buyer
+left join availability_codes a on a.id = r.availability
+order by r.seller, r.buyer;
@@ -0,0 +1,3 @@
+select water_system_no
+from water_systems
+where buyers_scraped is not null;
@@ -0,0 +1,7 @@
+select
+    water_system_no,
+    name,
+    state_code,
+    is_no
+from water_systems
+where water_system_no = :water_system_no;
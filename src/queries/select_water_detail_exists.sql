@@ -0,0 +1,6 @@
+select exists (
+    select 1
+    from water_systems
+    where water_system_no = :water_system_no
+        and buyers_scraped is not null
+);
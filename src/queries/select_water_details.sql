@@ -0,0 +1,15 @@
+select
+    water_system_no,
+    name,
+    state_code,
+    is_no,
+    activity_status,
+    source_water_type,
+    county,
+    federal_source,
+    address,
+    city,
+    zip,
+    last_updated
+from water_systems
+order by water_system_no;
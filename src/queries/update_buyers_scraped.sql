@@ -0,0 +1,3 @@
+update water_systems
+set buyers_scraped = :buyers_scraped
+where water_system_no = :water_system_no;
@@ -0,0 +1,12 @@
+update water_systems
+set
+    buyer_count = case when buyers_scraped = 1 then (
+        select count(*)
+        from water_buyer_relationships
+        where seller = water_systems.water_system_no
+    ) end,
+    seller_count = (
+        select count(*)
+        from water_buyer_relationships
+        where buyer = water_systems.water_system_no
+    );
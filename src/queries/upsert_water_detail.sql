@@ -0,0 +1,45 @@
+insert into water_systems (
+    water_system_no, 
+    name, 
+    state_code, 
+    is_no,
+    activity_status,
+    source_water_type,
+    county,
+    federal_source,
+    address,
+    city,
+    zip,
+    last_updated,
+    created,
+    scraped_at
+)
+values (
+    :water_system_no, 
+    :water_system_name, 
+    :state_code, 
+    :is_no,
+    :activity_status,
+    :source_water_type,
+    :county,
+    :federal_source,
+    :address,
+    :city,
+    :zip,
+    :last_updated,
+    :created_timestamp,
+    :scraped_at
+)
+on conflict (water_system_no) do update set
+    name = excluded.name,
+    state_code = excluded.state_code,
+    is_no = coalesce(excluded.is_no, is_no),
+    activity_status = coalesce(excluded.activity_status, activity_status),
+    source_water_type = coalesce(excluded.source_water_type, source_water_type),
+    county = coalesce(excluded.county, county),
+    federal_source = coalesce(excluded.federal_source, federal_source),
+    address = coalesce(excluded.address, address),
+    city = coalesce(excluded.city, city),
+    zip = coalesce(excluded.zip, zip),
+    last_updated = coalesce(excluded.last_updated, last_updated),
+    scraped_at = excluded.scraped_at;
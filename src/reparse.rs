@@ -0,0 +1,113 @@
+use crate::cli::ReparseConfig;
+use crate::db;
+use crate::error::{Error, Result};
+use crate::http::{FetchResponse, PageValidators};
+use crate::models::{BuyerSellerRelationship, WaterDetail};
+use crate::parse::{self, PageData};
+
+// How the stored relationships of a seller differ after reparsing its page
+#[derive(Default)]
+struct RelationshipChanges {
+    added: usize,
+    removed: usize,
+    changed: usize // Same buyer, but a different population or availability
+}
+
+// Runs the parser over stored pages and replaces what was stored from them, without sending any requests.
+// Pages come from the page_html table (see --store-html), or from a directory of saved pages.
+pub fn run(config: &ReparseConfig) -> Result<()> {
+    let mut conn = db::open(&config.db_path)?;
+    let created_timestamp: String = chrono::prelude::Local::now().format("%m-%d-%Y").to_string();
+    let stored_pages: Vec<(String, Vec<u8>)> =
+        match &config.from_html {
+            Some(html_dir) => read_html_dir(html_dir)?,
+            None => db::select_page_html(&conn)?
+        };
+    log::info!("Reparsing {} stored pages...", stored_pages.len());
+    let mut pages_reparsed: usize = 0;
+    let mut pages_skipped: usize = 0;
+    let mut total_changes = RelationshipChanges::default();
+    for (ws_number, html) in stored_pages {
+        let ws_number: String = parse::normalize_ws_number(&ws_number);
+        // Keep the IS number and state code from the input that originally listed this system
+        let mut detail: WaterDetail =
+            db::select_water_detail(&ws_number, &conn)?
+                .unwrap_or_else(|| WaterDetail {
+                    st_code: parse::state_code_of(&ws_number),
+                    ws_number: ws_number.clone(),
+                    ..Default::default()
+                });
+        // The name is read from the page again in case the old parse got it wrong
+        detail.name = None;
+        let response = FetchResponse {
+            status_code: 200,
+            reason_phrase: "OK".to_string(),
+            body: String::from_utf8_lossy(&html).into_owned(),
+            validators: PageValidators::default(),
+            retry_after: None
+        };
+        let page: PageData = parse::parse_page(&detail, response, parse::Direction::Both);
+        if page.water_detail.name.is_none() {
+            log::warn!("Skipped stored page for {} because it could not be parsed. Missing: {}", ws_number, page.missing_page_elements.join(", "));
+            pages_skipped += 1;
+            continue;
+        }
+        let prior_relationships: Vec<BuyerSellerRelationship> = db::select_relationships_by_seller(&ws_number, &conn)?;
+        db::replace_page(&page, page.has_buyers_table(), &mut conn, &created_timestamp)?;
+        let reparsed_relationships: Vec<BuyerSellerRelationship> = db::select_relationships_by_seller(&ws_number, &conn)?;
+        let changes: RelationshipChanges = compare_relationships(&prior_relationships, &reparsed_relationships);
+        log::info!("Reparsed {}: {} relationships added, {} removed, {} changed.", ws_number, changes.added, changes.removed, changes.changed);
+        total_changes.added += changes.added;
+        total_changes.removed += changes.removed;
+        total_changes.changed += changes.changed;
+        pages_reparsed += 1;
+    }
+    log::info!("Updating buyer and seller counts...");
+    let degree_counts_updated: usize = db::update_degree_counts(&conn)?;
+    log::info!("Updated buyer and seller counts of {} water details.", degree_counts_updated);
+    log::info!("Reparse summary:");
+    log::info!("\tPages reparsed: {}", pages_reparsed);
+    log::info!("\tPages skipped: {}", pages_skipped);
+    log::info!("\tRelationships added: {}", total_changes.added);
+    log::info!("\tRelationships removed: {}", total_changes.removed);
+    log::info!("\tRelationships changed: {}", total_changes.changed);
+    conn.close().map_err(|(_, e)| Error::Database(e))?;
+    Ok(())
+}
+
+// Reads every .html or .htm file in a directory. Each file is named after the water system number of its page (i.e., TX2270192.html).
+fn read_html_dir(html_dir: &std::path::Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let read_dir_error = |source| Error::InputIo { path: html_dir.to_path_buf(), source };
+    let mut html_file_paths: Vec<std::path::PathBuf> =
+        std::fs::read_dir(html_dir)
+            .map_err(read_dir_error)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<std::path::PathBuf>>>()
+            .map_err(read_dir_error)?;
+    html_file_paths.retain(|path| path.extension().is_some_and(|ext| ext == "html" || ext == "htm"));
+    html_file_paths.sort();
+    html_file_paths
+        .into_iter()
+        .map(|path| {
+            let html: Vec<u8> = std::fs::read(&path).map_err(|source| Error::InputIo { path: path.clone(), source })?;
+            let ws_number: String = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            Ok((ws_number, html))
+        })
+        .collect()
+}
+
+// Matches relationships up by buyer. Both lists belong to the same seller.
+fn compare_relationships(prior: &[BuyerSellerRelationship], reparsed: &[BuyerSellerRelationship]) -> RelationshipChanges {
+    let prior_by_buyer: std::collections::HashMap<&String, &BuyerSellerRelationship> = prior.iter().map(|r| (&r.buyer, r)).collect();
+    let mut changes = RelationshipChanges::default();
+    for r in reparsed.iter() {
+        match prior_by_buyer.get(&r.buyer) {
+            None => changes.added += 1,
+            Some(p) if p.population != r.population || p.availability != r.availability => changes.changed += 1,
+            Some(_) => ()
+        }
+    }
+    let reparsed_buyers: std::collections::HashSet<&String> = reparsed.iter().map(|r| &r.buyer).collect();
+    changes.removed = prior.iter().filter(|p| !reparsed_buyers.contains(&p.buyer)).count();
+    changes
+}
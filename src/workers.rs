@@ -0,0 +1,243 @@
+use crate::cache::PageCache;
+use crate::error::{Error, Result};
+use crate::http::{self, FetchResponse, HttpFetcher, PageValidators, RateLimiter};
+use crate::models::WaterDetail;
+use crate::parse::{self, Direction, PageData};
+
+// How many times a row is sent again after a 429 Too Many Requests response, waiting as long as the website asks each time.
+// These don't use up --retries. Once they run out, the 429 is retried like any other transient failure.
+static MAX_RATE_LIMITED_RETRIES: u32 = 5;
+
+// One input row waiting to be fetched. Anything that needs the database is looked up before the workers start,
+// since only the main thread uses the database connection.
+pub struct QueuedRow {
+    pub row: usize, // Row number shown in messages: the row's line in its input file, or its position if that isn't known (i.e., rows found by --max-depth)
+    pub detail: WaterDetail,
+    pub already_scraped: bool, // Set by --resume when the row's page is already stored
+    pub validators: Option<PageValidators>
+}
+
+// What a worker did with one input row
+pub enum FetchOutcome {
+    AlreadyScraped, // Skipped without sending a request
+    OutOfRequests, // --max-requests was reached before the row's first request
+    Cached(Box<PageData>), // Parsed from the --cache-dir copy of the page, without sending a request
+    Fetched {
+        result: Box<Result<PageData>>,
+        row_started: std::time::Instant,
+        latency: std::time::Duration, // Time spent waiting on the website's responses, not counting the delay between requests or parsing
+        responses: http::ResponseTally // Every response to the row's requests, including the ones that were retried
+    }
+}
+
+// Everything the workers share. Rows are claimed in input order.
+pub struct FetchQueue {
+    rows: Vec<QueuedRow>,
+    fetcher: HttpFetcher,
+    rate_limiter: Option<std::sync::Arc<RateLimiter>>,
+    page_cache: Option<PageCache>,
+    direction: Direction,
+    delay_pacer: std::sync::Arc<RateLimiter>, // Spaces out requests by --delay and --delay-jitter, no matter which worker sends them
+    delay: u32, // Base of the retry backoff
+    retries: u32,
+    max_requests: Option<u32>,
+    next_row: std::sync::atomic::AtomicUsize,
+    requests_sent: std::sync::atomic::AtomicU32, // Includes retries
+    stopped: std::sync::atomic::AtomicBool
+}
+
+impl FetchQueue {
+    pub fn new(
+        rows: Vec<QueuedRow>,
+        fetcher: HttpFetcher,
+        rate_limiter: Option<std::sync::Arc<RateLimiter>>,
+        delay_pacer: std::sync::Arc<RateLimiter>,
+        delay: u32,
+        retries: u32,
+        max_requests: Option<u32>
+    ) -> FetchQueue {
+        FetchQueue {
+            rows,
+            fetcher,
+            rate_limiter,
+            page_cache: None,
+            direction: Direction::Both,
+            delay_pacer,
+            delay,
+            retries,
+            max_requests,
+            next_row: std::sync::atomic::AtomicUsize::new(0),
+            requests_sent: std::sync::atomic::AtomicU32::new(0),
+            stopped: std::sync::atomic::AtomicBool::new(false)
+        }
+    }
+
+    // Pages are loaded from the cache when possible, and every page fetched is saved to it
+    pub fn with_page_cache(mut self, page_cache: Option<PageCache>) -> FetchQueue {
+        self.page_cache = page_cache;
+        self
+    }
+
+    // Only the relationship tables of this direction are read from each page (see --direction)
+    pub fn with_direction(mut self, direction: Direction) -> FetchQueue {
+        self.direction = direction;
+        self
+    }
+
+    // Claims rows and sends back what happened to each one, until every row is claimed or the queue is stopped
+    fn work(&self, results: std::sync::mpsc::Sender<(usize, FetchOutcome)>) {
+        while !self.stopped.load(std::sync::atomic::Ordering::SeqCst) {
+            let idx: usize = self.next_row.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let Some(row) = self.rows.get(idx) else {
+                break
+            };
+            let outcome: FetchOutcome = self.fetch_row(row);
+            // Once the budget is spent, no other row can be fetched either
+            if matches!(outcome, FetchOutcome::OutOfRequests) {
+                self.stop();
+            }
+            if results.send((idx, outcome)).is_err() {
+                break
+            }
+        }
+    }
+
+    fn fetch_row(&self, row: &QueuedRow) -> FetchOutcome {
+        if row.already_scraped {
+            return FetchOutcome::AlreadyScraped
+        }
+        let detail: &WaterDetail = &row.detail;
+        if let Some(html) = self.page_cache.as_ref().and_then(|cache| cache.load(detail)) {
+            log::info!("Loading water detail {} (Row {}) from the page cache...", detail.ws_number, row.row);
+            let response = FetchResponse {
+                status_code: 200,
+                reason_phrase: "OK".to_string(),
+                body: html,
+                validators: PageValidators::default(),
+                retry_after: None
+            };
+            return FetchOutcome::Cached(Box::new(parse::parse_page(detail, response, self.direction)))
+        }
+        if !self.reserve_request() {
+            return FetchOutcome::OutOfRequests
+        }
+        log::info!("Scraping water detail {} (Row {})... ({})", detail.ws_number, row.row, detail.url());
+        let row_started: std::time::Instant = std::time::Instant::now();
+        let mut latency: std::time::Duration = std::time::Duration::ZERO;
+        let mut retries_used: u32 = 0;
+        let mut rate_limited_retries: u32 = 0;
+        let mut responses = http::ResponseTally::default();
+        loop {
+            self.delay_pacer.acquire();
+            if let Some(limiter) = self.rate_limiter.as_ref() {
+                limiter.acquire();
+            }
+            let request_started: std::time::Instant = std::time::Instant::now();
+            let response: Result<FetchResponse> = parse::fetch_detail(detail, &self.fetcher, row.validators.as_ref());
+            latency += request_started.elapsed();
+            let result: Result<PageData> = response.map(|response| parse::parse_page(detail, response, self.direction));
+            match &result {
+                Ok(page) => responses.record_status(page.status_code),
+                Err(e) => responses.record_error(e)
+            }
+            match result {
+                // The website is asking for fewer requests, so every worker waits before the row is sent again
+                Err(Error::Status { status_code: 429, retry_after, .. }) if rate_limited_retries < MAX_RATE_LIMITED_RETRIES && self.reserve_request() => {
+                    rate_limited_retries += 1;
+                    let wait: std::time::Duration = retry_after.unwrap_or(http::DEFAULT_RETRY_AFTER);
+                    log::warn!("Got 429 Too Many Requests for water detail {}. Pausing all requests for {} seconds{}, then retrying it (attempt {} of {}). CSV Row number: {}",
+                        detail.ws_number, wait.as_secs(), if retry_after.is_some() { " as asked by the website" } else { "" },
+                        rate_limited_retries, MAX_RATE_LIMITED_RETRIES, row.row);
+                    self.delay_pacer.hold_off(wait);
+                },
+                // The retry is only sent if it fits within --max-requests
+                Err(e) if e.is_transient() && retries_used < self.retries && self.reserve_request() => {
+                    retries_used += 1;
+                    let backoff: u32 = http::backoff_delay(self.delay, retries_used);
+                    log::warn!("Request for water detail {} failed. Retrying in {} milliseconds (attempt {} of {}). CSV Row number: {} | Error: {}",
+                        detail.ws_number, backoff, retries_used+1, self.retries+1, row.row, e);
+                    std::thread::sleep(std::time::Duration::from_millis(backoff.into()));
+                },
+                result => {
+                    if let (Ok(page), Some(cache)) = (&result, self.page_cache.as_ref()) {
+                        cache.save(detail, &page.html);
+                    }
+                    return FetchOutcome::Fetched { result: Box::new(result), row_started, latency, responses }
+                }
+            }
+        }
+    }
+
+    // Counts a request against --max-requests. Returns false, without counting it, if the budget is already spent.
+    fn reserve_request(& self) -> bool {
+        self.requests_sent
+            .fetch_update(std::sync::atomic::Ordering::SeqCst, std::sync::atomic::Ordering::SeqCst, |sent| {
+                match self.max_requests {
+                    Some(max) if sent >= max => None,
+                    _ => Some(sent + 1)
+                }
+            })
+            .is_ok()
+    }
+
+    fn stop(& self) {
+        self.stopped.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+// Fetches rows on --jobs threads and hands the outcomes back in input order, holding back any that finish early.
+// Dropping the pool stops the workers from claiming more rows and waits for the requests already sent.
+pub struct FetchPool {
+    queue: std::sync::Arc<FetchQueue>,
+    workers: Vec<std::thread::JoinHandle<()>>,
+    results: std::sync::mpsc::Receiver<(usize, FetchOutcome)>,
+    finished_early: std::collections::BTreeMap<usize, FetchOutcome>
+}
+
+impl FetchPool {
+    pub fn start(queue: FetchQueue, jobs: u32) -> FetchPool {
+        let queue: std::sync::Arc<FetchQueue> = std::sync::Arc::new(queue);
+        let (sender, results) = std::sync::mpsc::channel();
+        let workers: Vec<std::thread::JoinHandle<()>> =
+            (0..jobs)
+                .map(|_| {
+                    let queue: std::sync::Arc<FetchQueue> = std::sync::Arc::clone(&queue);
+                    let sender: std::sync::mpsc::Sender<(usize, FetchOutcome)> = sender.clone();
+                    std::thread::spawn(move || queue.work(sender))
+                })
+                .collect();
+        FetchPool { queue, workers, results, finished_early: std::collections::BTreeMap::new() }
+    }
+
+    // Waits for the outcome of the given row. None means the workers stopped before claiming it.
+    pub fn take(&mut self, idx: usize) -> Option<FetchOutcome> {
+        loop {
+            if let Some(outcome) = self.finished_early.remove(&idx) {
+                return Some(outcome)
+            }
+            let (finished_idx, outcome) = self.results.recv().ok()?;
+            self.finished_early.insert(finished_idx, outcome);
+        }
+    }
+
+    // Stops the workers and returns the number of requests they sent, including retries
+    pub fn finish(mut self) -> u32 {
+        self.stop_workers();
+        self.queue.requests_sent.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    fn stop_workers(&mut self) {
+        self.queue.stop();
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                log::error!("A scraping worker thread panicked.");
+            }
+        }
+    }
+}
+
+impl Drop for FetchPool {
+    fn drop(&mut self) {
+        self.stop_workers();
+    }
+}
@@ -0,0 +1,93 @@
+// Helpers shared by the integration tests
+
+// Reads a saved page from tests/fixtures
+pub fn fixture(name: &str) -> String {
+    let path: std::path::PathBuf = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("Unable to read fixture {}. {}", path.display(), e))
+}
+
+// Parses a fixture as if it were the page fetched for the given water system.
+// Not every test file parses whole pages, hence the allow.
+#[allow(dead_code)]
+pub fn parse_fixture(name: &str, ws_number: &str) -> tceq_scraper::parse::PageData {
+    parse_fixture_in_direction(name, ws_number, tceq_scraper::parse::Direction::Both)
+}
+
+// Same as parse_fixture, but only reads the relationship tables of the given direction
+#[allow(dead_code)]
+pub fn parse_fixture_in_direction(name: &str, ws_number: &str, direction: tceq_scraper::parse::Direction) -> tceq_scraper::parse::PageData {
+    let detail = tceq_scraper::models::WaterDetail {
+        is_number: Some("5969".to_string()),
+        st_code: "TX".to_string(),
+        ws_number: ws_number.to_string(),
+        ..Default::default()
+    };
+    let response = tceq_scraper::http::FetchResponse {
+        status_code: 200,
+        reason_phrase: "OK".to_string(),
+        body: fixture(name),
+        validators: tceq_scraper::http::PageValidators::default(),
+        retry_after: None
+    };
+    tceq_scraper::parse::parse_page(&detail, response, direction)
+}
+
+// A file in the temp directory with the given contents, removed again when dropped
+#[allow(dead_code)]
+pub struct TempFile {
+    pub path: std::path::PathBuf
+}
+
+#[allow(dead_code)]
+impl TempFile {
+    pub fn new(name: &str, contents: &[u8]) -> TempFile {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("tceq_scraper_{}_{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap_or_else(|e| panic!("Unable to write {}. {}", path.display(), e));
+        TempFile { path }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// A fresh directory in the temp directory, removed again along with everything in it when dropped
+#[allow(dead_code)]
+pub struct TempDir {
+    pub path: std::path::PathBuf
+}
+
+#[allow(dead_code)]
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("tceq_scraper_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+// The scrape settings for the given arguments, as if they were passed on the command line
+#[allow(dead_code)]
+pub fn scrape_config(args: &[&str]) -> tceq_scraper::cli::Config {
+    match tceq_scraper::cli::parse_args_from(std::iter::once("tceq-scraper").chain(args.iter().copied())).unwrap() {
+        tceq_scraper::cli::Action::Scrape(config) => *config,
+        _ => panic!("The arguments aren't for a scrape: {}", args.join(" "))
+    }
+}
+
+// The scraper binary, with RUST_LOG cleared so its messages are the same as with no environment set
+#[allow(dead_code)]
+pub fn scraper_command() -> std::process::Command {
+    let mut command = std::process::Command::new(env!("CARGO_BIN_EXE_tceq-scraper"));
+    command.env_remove("RUST_LOG");
+    command
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::TempDir;
+
+// Scrapes TX0000001, whose cached page lists no buyers, into the database in the directory
+fn scrape_no_buyers_page(dir: &TempDir, args: &[&str]) -> std::process::Output {
+    let cache_dir: std::path::PathBuf = dir.path.join("cache");
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(cache_dir.join("1_TX0000001.html"), common::fixture("no_buyers_page.html")).unwrap();
+    std::fs::write(dir.path.join("input.csv"), "is_number,st_code,ws_number\n1,TX,TX0000001\n").unwrap();
+    let run = 
+        common::scraper_command()
+            .arg("-i").arg(dir.path.join("input.csv"))
+            .arg("--cache-dir").arg(&cache_dir)
+            .arg("--db").arg(dir.path.join("empty_buyers.db3"))
+            .arg("--no-default-output")
+            .args(args)
+            .output()
+            .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    run
+}
+
+fn stored_buyer_counts(dir: &TempDir) -> Vec<(Option<bool>, Option<i64>)> {
+    let conn = rusqlite::Connection::open(dir.path.join("empty_buyers.db3")).unwrap();
+    let mut stmt = conn.prepare("select buyers_scraped, buyer_count from water_systems where water_system_no = 'TX0000001'").unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().collect::<rusqlite::Result<Vec<_>>>().unwrap()
+}
+
+#[test]
+fn stores_systems_with_no_buyers() {
+    let dir = TempDir::new("stores_empty_buyers");
+    scrape_no_buyers_page(&dir, &[]);
+    assert_eq!(stored_buyer_counts(&dir), [(Some(true), Some(0))]);
+    // So the next --only-new run doesn't fetch it again
+    let run = scrape_no_buyers_page(&dir, &["--only-new"]);
+    assert!(String::from_utf8_lossy(&run.stderr).contains("Found 0 new water systems. Skipping 1 that are already in the database."));
+}
+
+#[test]
+fn skips_systems_with_no_buyers_when_asked() {
+    let dir = TempDir::new("skips_empty_buyers");
+    let run = scrape_no_buyers_page(&dir, &["--skip-empty-buyers"]);
+    assert!(String::from_utf8_lossy(&run.stderr).contains("Skipping TX0000001 because it has no buyers."));
+    assert!(stored_buyer_counts(&dir).is_empty());
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::TempDir;
+
+// Scrapes the given (is_number, ws_number, fixture) rows in order, each loaded from --cache-dir
+fn scrape_cached_pages(dir: &TempDir, pages: &[(&str, &str, &str)]) -> std::process::Output {
+    let cache_dir: std::path::PathBuf = dir.path.join("cache");
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    let mut input: String = "is_number,st_code,ws_number\n".to_string();
+    for (is_number, ws_number, fixture) in pages {
+        std::fs::write(cache_dir.join(format!("{}_{}.html", is_number, ws_number)), common::fixture(fixture)).unwrap();
+        input += &format!("{},TX,{}\n", is_number, ws_number);
+    }
+    std::fs::write(dir.path.join("input.csv"), input).unwrap();
+    common::scraper_command()
+        .arg("-i").arg(dir.path.join("input.csv"))
+        .arg("--cache-dir").arg(&cache_dir)
+        .arg("--db").arg(dir.path.join("first_page_check.db3"))
+        .arg("--manifest").arg(dir.path.join("manifest.csv"))
+        .args(["-o", "-"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn accepts_first_page_without_buyers_table() {
+    let dir = TempDir::new("first_page_without_buyers_table");
+    let run = scrape_cached_pages(&dir, &[("2", "TX1500002", "no_buyers_table_page.html"), ("5969", "TX2270192", "detail_page.html")]);
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    let mut reader = csv::Reader::from_reader(run.stdout.as_slice());
+    let ws_numbers: Vec<String> = reader.records().map(|record| record.unwrap()[2].to_string()).collect();
+    assert_eq!(ws_numbers, ["TX1500002", "TX2270192", "TX2270192"]);
+}
+
+#[test]
+fn stops_at_first_page_without_detail_table() {
+    let dir = TempDir::new("first_page_without_detail_table");
+    let run = scrape_cached_pages(&dir, &[("5969", "TX2270192", "error_page.html"), ("1", "TX0000001", "no_buyers_page.html")]);
+    let stderr: String = String::from_utf8_lossy(&run.stderr).into_owned();
+    assert!(!run.status.success());
+    assert!(stderr.contains("Stopping at CSV row 2 because the first page scraped is missing what the parser looks for."), "{}", stderr);
+    assert!(!stderr.contains("Finished scraping TX0000001"), "{}", stderr);
+    // The run still finishes up: the summary is printed and the output and manifest are flushed
+    assert!(stderr.contains("Run summary:"), "{}", stderr);
+    let mut reader = csv::Reader::from_reader(run.stdout.as_slice());
+    assert_eq!(reader.records().count(), 0);
+    let manifest: String = std::fs::read_to_string(dir.path.join("manifest.csv")).unwrap();
+    assert_eq!(manifest.lines().count(), 2, "{}", manifest);
+    assert!(manifest.lines().nth(1).unwrap().starts_with("2,TX2270192,"), "{}", manifest);
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::TempDir;
+use tceq_scraper::db;
+use tceq_scraper::models::WaterDetail;
+use tceq_scraper::parse::PageData;
+
+// A copy of the database shipped with the first release, which predates every added column
+fn old_database(dir: &TempDir) -> std::path::PathBuf {
+    let path: std::path::PathBuf = dir.path.join("old.db3");
+    std::fs::copy(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("water_buyer_relationships.db3"), &path).unwrap();
+    path
+}
+
+#[test]
+fn updates_database_from_the_first_release() {
+    let dir = TempDir::new("migrate_schema");
+    let db_path: std::path::PathBuf = old_database(&dir);
+    let mut conn = db::open(&db_path).unwrap();
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    let stored: WaterDetail = db::select_water_details(&conn).unwrap().into_iter().find(|d| d.ws_number == "TX2270192").unwrap();
+    assert_eq!(stored.county.as_deref(), Some("TRAVIS"));
+    assert_eq!(db::select_relationships_by_seller(&"TX2270192".to_string(), &conn).unwrap().len(), 2);
+    // Opening it again finds nothing left to update
+    drop(conn);
+    db::open(&db_path).unwrap();
+    db::open_read_only(&db_path).unwrap();
+}
+
+#[test]
+fn refuses_old_database_when_read_only() {
+    let dir = TempDir::new("migrate_schema_read_only");
+    let db_path: std::path::PathBuf = old_database(&dir);
+    assert!(db::open_read_only(&db_path).is_err());
+}
+
+#[test]
+fn allows_blank_population_in_database_from_the_first_release() {
+    let dir = TempDir::new("migrate_population");
+    let db_path: std::path::PathBuf = old_database(&dir);
+    {
+        let old_conn = rusqlite::Connection::open(&db_path).unwrap();
+        old_conn.execute_batch("
+            insert into water_systems (water_system_no, name, state_code, is_no, created) values ('TX0570004', 'BIG CITY', 'TX', '100', '01-01-2020');
+            insert into water_systems (water_system_no, name, state_code, is_no, created) values ('TX2270192', 'CITY OF TEST', 'TX', '5969', '01-01-2020');
+            insert into water_buyer_relationships (seller, buyer, population, availability, created) values ('TX0570004', 'TX2270192', 5000, 4, '01-01-2020');
+        ").unwrap();
+    }
+    let mut conn = db::open(&db_path).unwrap();
+    // Rows from before the update are kept, with the population text filled in from the number
+    let relationships = db::select_relationships_by_seller(&"TX0570004".to_string(), &conn).unwrap();
+    assert_eq!(relationships.len(), 1);
+    assert_eq!(relationships[0].population, "5000");
+    // A population that isn't a number used to roll back the whole page
+    let mut page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    page.relationships[0].population = "N/A".to_string();
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    let stored_population: (Option<i64>, String) = 
+        conn.query_row("select population, population_raw from water_buyer_relationships where buyer = ?1", [&page.relationships[0].buyer], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+    assert_eq!(stored_population, (None, "N/A".to_string()));
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use common::TempDir;
+use tceq_scraper::db;
+
+#[test]
+fn skips_systems_already_in_the_database() {
+    let dir = TempDir::new("only_new");
+    let db_path: std::path::PathBuf = dir.path.join("only_new.db3");
+    // An earlier run scraped TX2270192, which also stored its buyers without scraping them
+    let mut conn = db::open(&db_path).unwrap();
+    let page = common::parse_fixture("detail_page.html", "TX2270192");
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    drop(conn);
+    std::fs::write(dir.path.join("input.csv"), "is_number,st_code,ws_number\n5969,TX,TX2270192\n100,TX,TX0570004\n").unwrap();
+    // Both pages are in --cache-dir, so no request is sent either way
+    let cache_dir: std::path::PathBuf = dir.path.join("cache");
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(cache_dir.join("5969_TX2270192.html"), common::fixture("detail_page.html")).unwrap();
+    std::fs::write(cache_dir.join("100_TX0570004.html"), common::fixture("seller_page.html")).unwrap();
+    let run = 
+        common::scraper_command()
+            .arg("-i").arg(dir.path.join("input.csv"))
+            .arg("--cache-dir").arg(&cache_dir)
+            .arg("--db").arg(&db_path)
+            .args(["-o", "-", "--only-new"])
+            .output()
+            .unwrap();
+    let stderr: String = String::from_utf8_lossy(&run.stderr).into_owned();
+    assert!(run.status.success(), "{}", stderr);
+    assert!(stderr.contains("Found 1 new water systems. Skipping 1 that are already in the database."), "{}", stderr);
+    assert!(!stderr.contains("Loading water detail TX2270192"), "{}", stderr);
+    let mut reader = csv::Reader::from_reader(run.stdout.as_slice());
+    let ws_numbers: Vec<String> = reader.records().map(|record| record.unwrap()[2].to_string()).collect();
+    assert_eq!(ws_numbers, ["TX0570004"]);
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use tceq_scraper::output::{self, OutputWriter, OUTPUT_FIELDS};
+
+// A writer whose bytes can still be read after it's handed to the csv writer
+#[derive(Clone, Default)]
+struct SharedBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Writes the fixture as csv and returns its header row
+fn write_header(fixture: &str) -> Vec<String> {
+    let buffer = SharedBuffer::default();
+    let mut writer = OutputWriter::Csv(Box::new(csv::Writer::from_writer(Box::new(buffer.clone()) as Box<dyn std::io::Write>)));
+    output::write_page(&mut writer, &common::parse_fixture(fixture, "TX2270192"), &[]).unwrap();
+    output::finish(writer).unwrap();
+    let written: Vec<u8> = buffer.0.borrow().clone();
+    let mut reader = csv::Reader::from_reader(written.as_slice());
+    reader.headers().unwrap().iter().map(str::to_string).collect()
+}
+
+#[test]
+fn header_matches_output_fields() {
+    assert_eq!(write_header("detail_page.html"), OUTPUT_FIELDS);
+    // i.e., a row with blank relationship columns
+    assert_eq!(write_header("no_buyers_page.html"), OUTPUT_FIELDS);
+}
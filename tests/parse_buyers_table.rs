@@ -0,0 +1,105 @@
+mod common;
+
+use tceq_scraper::models::BuyerSellerRelationship;
+use tceq_scraper::parse;
+
+// Parses the "Buyers of Water" table of a fixture
+fn parse_fixture(name: &str) -> (Vec<BuyerSellerRelationship>, usize) {
+    let dom = scraper::Html::parse_document(&common::fixture(name));
+    let table_name: String = "Buyers of Water".to_string();
+    let table = parse::get_table_by_name(&table_name, &dom).expect("Fixture has no buyers table");
+    parse::parse_buyers_table(&table)
+}
+
+#[test]
+fn parses_normal_row() {
+    let (relationships, data_rows) = parse_fixture("buyers_normal.html");
+    assert_eq!(data_rows, 1);
+    assert_eq!(relationships.len(), 1);
+    let r: &BuyerSellerRelationship = &relationships[0];
+    assert_eq!(r.seller, "TX2270192");
+    assert_eq!(r.buyer_name, "SOME BUYER WSC");
+    assert_eq!(r.buyer, "TX1050176");
+    assert_eq!(r.population, "1,234");
+    assert_eq!(r.availability, "P");
+}
+
+#[test]
+fn parses_row_with_blank_availability() {
+    let (relationships, _) = parse_fixture("buyers_blank_availability.html");
+    assert_eq!(relationships.len(), 1);
+    assert_eq!(relationships[0].buyer, "TX1050013");
+    assert_eq!(relationships[0].population, "50");
+    assert_eq!(relationships[0].availability, "");
+}
+
+#[test]
+fn parses_row_with_sells_to_delimiter() {
+    let (relationships, _) = parse_fixture("buyers_sells_to.html");
+    assert_eq!(relationships.len(), 1);
+    let r: &BuyerSellerRelationship = &relationships[0];
+    assert_eq!(r.seller, "TX2270192");
+    assert_eq!(r.buyer_name, "SOME BUYER WSC");
+    assert_eq!(r.buyer, "TX1050176");
+    assert_eq!(r.population, "1,234");
+    assert_eq!(r.availability, "P");
+}
+
+#[test]
+fn parses_multi_row_table_and_skips_header_rows() {
+    let (relationships, data_rows) = parse_fixture("buyers_multi_row.html");
+    assert_eq!(data_rows, 3);
+    let buyers: Vec<&str> = relationships.iter().map(|r| r.buyer.as_str()).collect();
+    assert_eq!(buyers, vec!["TX1050176", "TX1050013", "TX1050099"]);
+    // Water system numbers are normalized and whitespace within names is collapsed
+    assert_eq!(relationships[2].seller, "TX2270192");
+    assert_eq!(relationships[2].buyer_name, "THIRD BUYER");
+    assert_eq!(relationships[2].availability, "E");
+}
+
+#[test]
+fn skips_total_row() {
+    let (relationships, data_rows) = parse_fixture("buyers_total_row.html");
+    assert_eq!(data_rows, 2);
+    let buyers: Vec<&str> = relationships.iter().map(|r| r.buyer.as_str()).collect();
+    assert_eq!(buyers, vec!["TX1050176", "TX1050013"]);
+    assert_eq!(relationships[1].population, "50");
+}
+
+#[test]
+fn finds_table_despite_header_spacing_colon_and_case() {
+    let dom = scraper::Html::parse_document(&common::fixture("buyers_normal.html").replace("Buyers of Water", "BUYERS  of\n Water:"));
+    assert!(parse::get_table_by_name("Buyers of Water", &dom).is_some());
+    assert!(parse::get_table_by_name("buyers of water", &dom).is_some());
+    assert!(parse::get_table_by_name("Sellers of Water", &dom).is_none());
+}
+
+#[test]
+fn keeps_hyphens_and_slashes_in_names() {
+    let (relationships, data_rows) = parse_fixture("buyers_punctuated_names.html");
+    assert_eq!(data_rows, 3);
+    let names: Vec<&str> = relationships.iter().map(|r| r.buyer_name.as_str()).collect();
+    assert_eq!(names, vec!["CITY OF X - Y WATER", "A/B WATER SUPPLY CORP", "NORTH - SOUTH WSC"]);
+    let buyers: Vec<&str> = relationships.iter().map(|r| r.buyer.as_str()).collect();
+    assert_eq!(buyers, vec!["TX1050176", "TX1050013", "TX1050099"]);
+    assert_eq!(relationships[0].seller, "TX2270192");
+    assert_eq!(relationships[0].availability, "P");
+    assert_eq!(relationships[1].availability, "");
+    // A row without the trailing slash still has its population
+    assert_eq!(relationships[2].population, "7,500");
+    assert_eq!(relationships[2].availability, "");
+}
+
+#[test]
+fn reads_one_field_per_cell() {
+    let (relationships, data_rows) = parse_fixture("buyers_cells.html");
+    assert_eq!(data_rows, 2);
+    let r: &BuyerSellerRelationship = &relationships[0];
+    assert_eq!(r.seller, "TX2270192");
+    assert_eq!(r.buyer_name, "CITY OF X - Y WATER");
+    assert_eq!(r.buyer, "TX1050176");
+    assert_eq!(r.population, "1,234");
+    assert_eq!(r.availability, "P");
+    assert_eq!(relationships[1].buyer_name, "A/B WATER SUPPLY CORP");
+    assert_eq!(relationships[1].availability, "");
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use tceq_scraper::models::WaterDetail;
+use tceq_scraper::parse::{self, PageData};
+
+#[test]
+fn parses_water_system_details() {
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    let detail: &WaterDetail = &page.water_detail;
+    assert!(page.missing_page_elements.is_empty());
+    assert_eq!(detail.name.as_deref(), Some("CITY OF TEST"));
+    assert_eq!(detail.activity_status.as_deref(), Some("Active"));
+    assert_eq!(detail.source_water_type.as_deref(), Some("Surface Water"));
+    assert_eq!(detail.county.as_deref(), Some("TRAVIS"));
+    assert_eq!(detail.federal_source.as_deref(), Some("SW"));
+    assert_eq!(detail.address.as_deref(), Some("100 MAIN ST"));
+    assert_eq!(detail.city.as_deref(), Some("AUSTIN"));
+    // The label and value share a cell
+    assert_eq!(detail.zip.as_deref(), Some("78701-1234"));
+}
+
+#[test]
+fn parses_last_updated_date() {
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    let detail: &WaterDetail = &page.water_detail;
+    assert_eq!(detail.last_updated.as_deref(), Some("2024-03-15"));
+    let date = |text: &str| chrono::NaiveDate::parse_from_str(text, "%Y-%m-%d").ok();
+    assert!(detail.is_updated_between(date("2024-03-15"), date("2024-03-15")));
+    assert!(!detail.is_updated_between(date("2024-03-16"), None));
+    assert!(!detail.is_updated_between(None, date("2024-03-14")));
+    assert_eq!(parse::parse_page_date("15-MAR-24"), date("2024-03-15"));
+    assert_eq!(parse::parse_page_date("sometime"), None);
+    // A page without a date is kept whatever the range
+    let no_date: PageData = common::parse_fixture("no_buyers_page.html", "TX0000001");
+    assert!(no_date.water_detail.is_updated_between(date("2030-01-01"), None));
+}
+
+#[test]
+fn parses_buyers_and_sellers() {
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    assert!(page.relationship_count_matches());
+    assert_eq!(page.relationships.len(), 2);
+    assert_eq!(page.relationships[0].seller_name, "CITY OF TEST");
+    assert_eq!(page.seller_relationships.len(), 1);
+    let seller = &page.seller_relationships[0];
+    assert_eq!(seller.seller, "TX0570004");
+    assert_eq!(seller.seller_name, "BIG CITY");
+    assert_eq!(seller.buyer, "TX2270192");
+    assert_eq!(seller.buyer_name, "CITY OF TEST");
+    assert_eq!(seller.population, "5,000");
+}
+
+#[test]
+fn skips_tables_outside_the_direction() {
+    let buyers_only: PageData = common::parse_fixture_in_direction("detail_page.html", "TX2270192", parse::Direction::Buyers);
+    assert_eq!(buyers_only.relationships.len(), 2);
+    assert!(buyers_only.seller_relationships.is_empty());
+    let sellers_only: PageData = common::parse_fixture_in_direction("detail_page.html", "TX2270192", parse::Direction::Sellers);
+    assert!(sellers_only.relationships.is_empty());
+    assert_eq!(sellers_only.seller_relationships.len(), 1);
+    // The skipped table isn't reported as missing, even on a page that doesn't have it
+    assert!(sellers_only.missing_page_elements.is_empty());
+    assert!(sellers_only.relationship_count_matches());
+    let error_page: PageData = common::parse_fixture_in_direction("error_page.html", "TX2270192", parse::Direction::Sellers);
+    assert!(!error_page.missing_page_elements.contains(&"Buyers of Water"));
+}
+
+#[test]
+fn keeps_annotated_population() {
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    assert_eq!(page.relationships[0].population, "1,234*");
+    assert_eq!(parse::parse_population(&page.relationships[0].population), Some(1234));
+    assert_eq!(parse::parse_population("100-200"), Some(100));
+    assert_eq!(parse::parse_population(""), None);
+}
+
+#[test]
+fn parses_page_without_buyers() {
+    // Also covers headers rendered as tbody cells, the older "PWS Name:" label, and an inactive system
+    let page: PageData = common::parse_fixture("no_buyers_page.html", "TX0000001");
+    assert!(page.missing_page_elements.is_empty());
+    assert!(page.has_buyers_table());
+    assert!(page.relationships.is_empty());
+    assert!(page.seller_relationships.is_empty());
+    assert_eq!(page.water_detail.name.as_deref(), Some("RURAL WSC"));
+    assert!(page.water_detail.is_inactive());
+    let source_water_type: &str = page.water_detail.source_water_type.as_deref().unwrap();
+    assert_eq!(parse::source_water_category(source_water_type), Some("purchased"));
+}
+
+#[test]
+fn parses_groundwater_source_type() {
+    let page: PageData = common::parse_fixture("groundwater_page.html", "TX1500001");
+    assert!(page.missing_page_elements.is_empty());
+    assert_eq!(page.water_detail.name.as_deref(), Some("HILL COUNTRY WSC"));
+    assert_eq!(page.water_detail.federal_source.as_deref(), Some("GW"));
+    let source_water_type: &str = page.water_detail.source_water_type.as_deref().unwrap();
+    assert_eq!(source_water_type, "Ground Water");
+    assert_eq!(parse::source_water_category(source_water_type), Some("groundwater"));
+    // Purchased water is covered by parses_page_without_buyers
+    let surface_page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    assert_eq!(surface_page.water_detail.source_water_type.as_deref().and_then(parse::source_water_category), Some("surface"));
+}
+
+#[test]
+fn parses_page_without_buyers_table() {
+    // Its buyers are unknown, but the page is still a real water system that can be stored
+    let page: PageData = common::parse_fixture("no_buyers_table_page.html", "TX1500002");
+    assert_eq!(page.missing_page_elements, ["Buyers of Water"]);
+    assert!(!page.has_buyers_table());
+    assert_eq!(page.water_detail.name.as_deref(), Some("LAKESIDE CAMP"));
+    assert!(page.check_structure().is_ok());
+}
+
+#[test]
+fn parses_page_without_detail_table() {
+    // An error page comes back as 200 OK but has none of the tables
+    let page: PageData = common::parse_fixture("error_page.html", "TX2270192");
+    assert_eq!(page.water_detail.name, None);
+    assert_eq!(page.water_detail.ws_number, "TX2270192");
+    assert!(page.missing_page_elements.contains(&"Water System Detail Information"));
+    assert!(!page.has_buyers_table());
+    assert!(page.relationships.is_empty());
+    assert!(page.check_structure().is_err());
+}
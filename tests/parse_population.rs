@@ -0,0 +1,21 @@
+use tceq_scraper::parse::parse_population;
+
+#[test]
+fn parses_thousands_separators_and_whitespace() {
+    assert_eq!(parse_population("1,234"), Some(1234));
+    assert_eq!(parse_population(" 12,345,678 "), Some(12345678));
+    assert_eq!(parse_population("1 234"), Some(1234));
+}
+
+#[test]
+fn parses_zero() {
+    assert_eq!(parse_population("0"), Some(0));
+}
+
+#[test]
+fn rejects_blank_and_non_numeric_cells() {
+    assert_eq!(parse_population(""), None);
+    assert_eq!(parse_population("   "), None);
+    assert_eq!(parse_population("N/A"), None);
+    assert_eq!(parse_population("unknown"), None);
+}
@@ -0,0 +1,23 @@
+use tceq_scraper::http::parse_retry_after;
+
+#[test]
+fn parses_seconds() {
+    assert_eq!(parse_retry_after("120"), Some(std::time::Duration::from_secs(120)));
+    assert_eq!(parse_retry_after(" 0 "), Some(std::time::Duration::ZERO));
+}
+
+#[test]
+fn parses_http_date() {
+    // A date in the past means the request can be sent again right away
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(std::time::Duration::ZERO));
+    let in_a_minute: String = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+    let wait: std::time::Duration = parse_retry_after(&in_a_minute).unwrap();
+    assert!(wait > std::time::Duration::from_secs(50) && wait <= std::time::Duration::from_secs(60));
+}
+
+#[test]
+fn rejects_other_values() {
+    assert_eq!(parse_retry_after(""), None);
+    assert_eq!(parse_retry_after("soon"), None);
+    assert_eq!(parse_retry_after("-5"), None);
+}
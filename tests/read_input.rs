@@ -0,0 +1,68 @@
+mod common;
+
+use common::TempFile;
+use tceq_scraper::input::{self, InputRows};
+
+// Reads one input file with the given arguments, i.e., its delimiter
+fn read_input(file: &TempFile, extra_args: &[&str]) -> InputRows {
+    let mut args: Vec<&str> = vec!["-i", file.path.to_str().unwrap(), "--no-default-output"];
+    args.extend_from_slice(extra_args);
+    input::read_water_details(&common::scrape_config(&args)).unwrap()
+}
+
+fn ws_numbers(input_rows: &InputRows) -> Vec<&str> {
+    input_rows.water_details.iter().map(|detail| detail.ws_number.as_str()).collect()
+}
+
+#[test]
+fn sniffs_each_delimiter() {
+    for (name, delimiter) in [("comma.csv", ","), ("tab.tsv", "\t"), ("semicolon.csv", ";")] {
+        let contents: String = ["is_number,st_code,ws_number", "5969,TX,TX2270192", "100,TX,TX1050013"].join("\n").replace(',', delimiter);
+        let input_rows: InputRows = read_input(&TempFile::new(name, contents.as_bytes()), &["--input-delimiter", "auto"]);
+        assert_eq!(ws_numbers(&input_rows), ["TX2270192", "TX1050013"], "{}", name);
+        assert_eq!(input_rows.water_details[0].is_number.as_deref(), Some("5969"), "{}", name);
+        assert_eq!(input_rows.malformed_rows_skipped, 0, "{}", name);
+    }
+}
+
+#[test]
+fn sniffs_the_most_common_delimiter_in_the_header() {
+    // The comma inside the quoted header is outnumbered by the semicolons
+    let contents: &str = "is_number;st_code;ws_number;\"name, city\"\n5969;TX;TX2270192;\"CITY OF TEST, AUSTIN\"\n";
+    let input_rows: InputRows = read_input(&TempFile::new("mixed.csv", contents.as_bytes()), &["--input-delimiter", "auto"]);
+    assert_eq!(ws_numbers(&input_rows), ["TX2270192"]);
+}
+
+#[test]
+fn reads_commas_by_default() {
+    let contents: &str = "is_number,st_code,ws_number\n5969,TX,TX2270192\n";
+    assert_eq!(ws_numbers(&read_input(&TempFile::new("default.csv", contents.as_bytes()), &[])), ["TX2270192"]);
+}
+
+#[test]
+fn reads_the_given_delimiter() {
+    // "auto" would pick the comma, since the quoted header has more commas than semicolons
+    let contents: &str = "is_number;st_code;ws_number;\"notes, i.e., a, b, or c\"\n5969;TX;TX2270192;\"a, b\"\n";
+    let input_rows: InputRows = read_input(&TempFile::new("given.csv", contents.as_bytes()), &["--input-delimiter", ";"]);
+    assert_eq!(ws_numbers(&input_rows), ["TX2270192"]);
+}
+
+#[test]
+fn keeps_one_row_per_padded_ws_number() {
+    let contents: &str = "is_number,st_code,ws_number\n5969,TX,TX2270192   \n5969,TX, tx2270192\n5969,TX,TX2270192\n";
+    let input_rows: InputRows = read_input(&TempFile::new("padded.csv", contents.as_bytes()), &[]);
+    assert_eq!(ws_numbers(&input_rows), ["TX2270192"]);
+    assert_eq!(input_rows.duplicate_rows_skipped, 2);
+}
+
+#[test]
+fn reads_windows_1252_input() {
+    // Saved by Excel on Windows, with an accented header ("número_sistema") and names
+    let path: std::path::PathBuf = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("latin1_input.csv");
+    let config = common::scrape_config(&["-i", path.to_str().unwrap(), "--no-default-output", "-w", "número_sistema", "--input-encoding", "windows-1252"]);
+    let input_rows: InputRows = input::read_water_details(&config).unwrap();
+    assert_eq!(ws_numbers(&input_rows), ["TX2270192", "TX1050013"]);
+    // Read as UTF-8, the accented header can't be matched
+    let config = common::scrape_config(&["-i", path.to_str().unwrap(), "--no-default-output", "-w", "número_sistema"]);
+    assert!(input::read_water_details(&config).is_err());
+}
@@ -0,0 +1,21 @@
+use tceq_scraper::models::{BuyerSellerRelationship, Record, WaterDetail};
+
+#[test]
+fn tags_records_by_kind() {
+    let relationship = BuyerSellerRelationship {
+        buyer: "TX1050176".to_string(),
+        seller: "TX2270192".to_string(),
+        population: "1,234".to_string(),
+        ..Default::default()
+    };
+    let json: String = serde_json::to_string(&Record::Relationship(relationship)).unwrap();
+    assert!(json.starts_with(r#"{"record_type":"relationship","buyer":"TX1050176""#));
+    let detail: Record = serde_json::from_str(r#"{"record_type":"water_detail","is_number":"5969","st_code":"TX","ws_number":"TX2270192"}"#).unwrap();
+    match detail {
+        Record::WaterDetail(WaterDetail { ws_number, is_number, .. }) => {
+            assert_eq!(ws_number, "TX2270192");
+            assert_eq!(is_number.as_deref(), Some("5969"));
+        },
+        Record::Relationship(_) => panic!("Parsed a water detail as a relationship")
+    }
+}
@@ -0,0 +1,40 @@
+mod common;
+
+use tceq_scraper::http::{FetchResponse, Fetcher, PageValidators};
+use tceq_scraper::parse;
+
+// Answers every request with the same saved page
+struct FixtureFetcher {
+    body: String
+}
+
+impl Fetcher for FixtureFetcher {
+    fn fetch(&self, _url: &str, _validators: Option<&PageValidators>) -> Result<FetchResponse, minreq::Error> {
+        Ok(FetchResponse {
+            status_code: 200,
+            reason_phrase: "OK".to_string(),
+            body: self.body.clone(),
+            validators: PageValidators::default(),
+            retry_after: None
+        })
+    }
+}
+
+#[test]
+fn resolves_is_number_from_search_results() {
+    let fetcher = FixtureFetcher { body: common::fixture("search_results.html") };
+    // The results list a system whose number shares the same prefix first
+    assert_eq!(parse::resolve_is_number("TX2270192", "TX", &fetcher).unwrap().as_deref(), Some("5969"));
+    assert_eq!(parse::resolve_is_number(" tx2270193 ", "TX", &fetcher).unwrap().as_deref(), Some("5970"));
+    assert_eq!(parse::resolve_is_number("TX0000001", "TX", &fetcher).unwrap(), None);
+}
+
+#[test]
+fn reads_water_detail_from_url() {
+    let detail = parse::water_detail_from_url("https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_is_number=5969&tinwsys_st_code=TX&wsnumber=TX2270192%20%20%20&DWWState=TX").unwrap();
+    assert_eq!(detail.is_number.as_deref(), Some("5969"));
+    assert_eq!(detail.st_code, "TX");
+    assert_eq!(detail.ws_number, "TX2270192");
+    assert!(parse::water_detail_from_url("https://dww2.tceq.texas.gov/DWW/JSP/WaterSystemDetail.jsp?tinwsys_st_code=TX&wsnumber=TX2270192").is_none());
+    assert!(parse::water_detail_from_url("https://dww2.tceq.texas.gov/DWW/JSP/SearchDispatch?number=TX2270192").is_none());
+}
@@ -0,0 +1,34 @@
+use tceq_scraper::error::Error;
+use tceq_scraper::http::ResponseTally;
+
+fn too_many_requests() -> Error {
+    Error::Status { status_code: 429, reason_phrase: "Too Many Requests".to_string(), retry_after: Some(std::time::Duration::from_secs(30)) }
+}
+
+#[test]
+fn counts_each_rate_limited_attempt() {
+    // i.e., a row that got two 429s, waited as asked each time, then got its page
+    let mut row_responses = ResponseTally::default();
+    row_responses.record_error(&too_many_requests());
+    row_responses.record_error(&too_many_requests());
+    row_responses.record_status(200);
+    let mut run_responses = ResponseTally::default();
+    run_responses.record_error(&too_many_requests());
+    run_responses.add(&row_responses);
+    assert_eq!(run_responses.status_codes.get(&429), Some(&3));
+    assert_eq!(run_responses.bucket(2), 1);
+    assert_eq!(run_responses.total(), 4);
+    assert_eq!(run_responses.to_string(), "2xx: 1 | 3xx: 0 | 4xx: 3 | 5xx: 0 | Transport errors: 0 (3 responses were 429)");
+}
+
+#[test]
+fn counts_the_response_behind_each_error() {
+    let mut responses = ResponseTally::default();
+    responses.record_error(&Error::NotModified);
+    responses.record_error(&Error::Request(minreq::Error::Other("connection reset")));
+    // Not a response from the website
+    responses.record_error(&Error::Config("bad config".to_string()));
+    assert_eq!(responses.status_codes.get(&304), Some(&1));
+    assert_eq!(responses.transport_errors, 1);
+    assert_eq!(responses.total(), 2);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::TempDir;
+
+// Scrapes TX2270192 from its cached page into the database in the directory and returns the run's log
+fn scrape_detail_page(dir: &TempDir, args: &[&str]) -> String {
+    let cache_dir: std::path::PathBuf = dir.path.join("cache");
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(cache_dir.join("5969_TX2270192.html"), common::fixture("detail_page.html")).unwrap();
+    std::fs::write(dir.path.join("input.csv"), "is_number,st_code,ws_number\n5969,TX,TX2270192\n").unwrap();
+    let run =
+        common::scraper_command()
+            .arg("-i").arg(dir.path.join("input.csv"))
+            .arg("--cache-dir").arg(&cache_dir)
+            .arg("--db").arg(dir.path.join("run_summary.db3"))
+            .arg("--no-default-output")
+            .args(args)
+            .output()
+            .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+    String::from_utf8_lossy(&run.stderr).to_string()
+}
+
+#[test]
+fn counts_relationships_from_both_tables() {
+    let dir = TempDir::new("summary_both_tables");
+    // Two buyers, plus the system it buys from
+    let log: String = scrape_detail_page(&dir, &[]);
+    assert!(log.contains("Scraped 1/1 pages, 3 relationships, 0 failures"), "{}", log);
+}
+
+#[test]
+fn counts_parsed_relationships_in_dry_run() {
+    let dir = TempDir::new("summary_dry_run");
+    // --dry-run needs an existing database
+    scrape_detail_page(&dir, &[]);
+    let log: String = scrape_detail_page(&dir, &["--dry-run"]);
+    assert!(log.contains("Parsed 1/1 pages, 3 relationships, 0 failures (dry run, nothing was stored)"), "{}", log);
+}
@@ -0,0 +1,31 @@
+mod common;
+
+use common::TempDir;
+use tceq_scraper::output::OUTPUT_FIELDS;
+
+#[test]
+fn writes_only_csv_to_stdout() {
+    let dir = TempDir::new("stdout_output");
+    std::fs::write(dir.path.join("input.csv"), "is_number,st_code,ws_number\n5969,TX,TX2270192\n").unwrap();
+    // The page is loaded from --cache-dir, so no request is sent
+    let cache_dir: std::path::PathBuf = dir.path.join("cache");
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(cache_dir.join("5969_TX2270192.html"), common::fixture("detail_page.html")).unwrap();
+    let run = 
+        common::scraper_command()
+            .arg("-i").arg(dir.path.join("input.csv"))
+            .arg("--cache-dir").arg(&cache_dir)
+            .arg("--db").arg(dir.path.join("stdout_output.db3"))
+            .args(["-o", "-"])
+            .output()
+            .unwrap();
+    let stderr: String = String::from_utf8_lossy(&run.stderr).into_owned();
+    assert!(run.status.success(), "{}", stderr);
+    let mut reader = csv::Reader::from_reader(run.stdout.as_slice());
+    assert_eq!(reader.headers().unwrap(), OUTPUT_FIELDS);
+    let buyers: Vec<String> = reader.records().map(|record| record.unwrap()[13].to_string()).collect();
+    assert_eq!(buyers, ["TX1050176", "TX1050013"]);
+    // Progress messages and the run summary go to stderr only
+    assert!(stderr.contains("Loading water detail TX2270192 (Row 2) from the page cache..."), "{}", stderr);
+    assert!(stderr.contains("Run summary:"), "{}", stderr);
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use common::TempDir;
+use tceq_scraper::db;
+use tceq_scraper::models::WaterDetail;
+use tceq_scraper::parse::PageData;
+
+#[test]
+fn stores_page_with_buyers_and_sellers() {
+    let dir = TempDir::new("stores_page");
+    let mut conn = db::open(&dir.path.join("stores_page.db3")).unwrap();
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    let mut stored_relationships: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut stored_relationships).unwrap();
+    assert_eq!(stored_relationships.len(), 2);
+    let stored = db::select_water_detail(&"TX2270192".to_string(), &conn).unwrap().unwrap();
+    assert_eq!(stored.name.as_deref(), Some("CITY OF TEST"));
+    assert_eq!(db::select_relationships_by_seller(&"TX2270192".to_string(), &conn).unwrap().len(), 2);
+    assert_eq!(db::select_relationships_by_seller(&"TX0570004".to_string(), &conn).unwrap().len(), 1);
+    // What the export subcommand writes: the page's own system, its two buyers, and its seller
+    assert_eq!(db::select_water_details(&conn).unwrap().len(), 4);
+    assert_eq!(db::select_relationships(&conn).unwrap().len(), 3);
+}
+
+#[test]
+fn stores_relationships_from_seller_to_buyer() {
+    // The page's system sells to TX1050176 in its buyers table and buys from TX0570004 in its sellers table
+    let dir = TempDir::new("relationship_direction");
+    let mut conn = db::open(&dir.path.join("relationship_direction.db3")).unwrap();
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    let mut stored_relationships: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut stored_relationships).unwrap();
+    let sold = db::select_relationships_by_seller(&"TX2270192".to_string(), &conn).unwrap();
+    let sold_to = sold.iter().find(|r| r.buyer == "TX1050176").unwrap();
+    assert_eq!(sold_to.seller, "TX2270192");
+    assert_eq!(sold_to.seller_name, "CITY OF TEST");
+    assert_eq!(sold_to.buyer_name, "SOME BUYER WSC");
+    let bought = db::select_relationships_by_seller(&"TX0570004".to_string(), &conn).unwrap();
+    assert_eq!(bought[0].seller_name, "BIG CITY");
+    assert_eq!(bought[0].buyer, "TX2270192");
+    assert_eq!(bought[0].buyer_name, "CITY OF TEST");
+    assert!(db::select_relationships_by_seller(&"TX1050176".to_string(), &conn).unwrap().is_empty());
+}
+
+#[test]
+fn records_when_each_row_was_scraped() {
+    let dir = TempDir::new("scraped_at");
+    let mut conn = db::open(&dir.path.join("scraped_at.db3")).unwrap();
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    let started = chrono::Utc::now() - chrono::Duration::seconds(1);
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    for table in ["water_systems", "water_buyer_relationships"] {
+        let scraped_at: Vec<String> = 
+            conn.prepare(&format!("select scraped_at from {}", table)).unwrap()
+                .query_map([], |row| row.get(0)).unwrap()
+                .collect::<rusqlite::Result<Vec<String>>>().unwrap();
+        assert!(!scraped_at.is_empty());
+        for timestamp in scraped_at {
+            let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp).unwrap();
+            assert!(timestamp >= started && timestamp <= chrono::Utc::now());
+        }
+    }
+}
+
+#[test]
+fn keeps_scraped_system_listed_as_another_systems_buyer() {
+    let dir = TempDir::new("keeps_buyer");
+    let mut conn = db::open(&dir.path.join("keeps_buyer.db3")).unwrap();
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    // TX0570004's own page lists TX2270192 as its buyer, with nothing but its name
+    let seller_page: PageData = common::parse_fixture("seller_page.html", "TX0570004");
+    db::store_page(&seller_page, true, false, &mut conn, &"02-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    let stored: WaterDetail = db::select_water_details(&conn).unwrap().into_iter().find(|d| d.ws_number == "TX2270192").unwrap();
+    assert_eq!(stored.is_number.as_deref(), Some("5969"));
+    assert_eq!(stored.activity_status.as_deref(), Some("Active"));
+    assert_eq!(stored.source_water_type.as_deref(), Some("Surface Water"));
+    assert_eq!(stored.county.as_deref(), Some("TRAVIS"));
+    assert_eq!(stored.address.as_deref(), Some("100 MAIN ST"));
+    assert_eq!(stored.last_updated.as_deref(), Some("2024-03-15"));
+    assert!(db::water_detail_exists(&"TX2270192".to_string(), &conn).unwrap());
+    // The buyer's relationship from the seller's page is still stored
+    assert_eq!(db::select_relationships_by_seller(&"TX0570004".to_string(), &conn).unwrap().len(), 1);
+}
+
+#[test]
+fn keeps_buyers_scraped_when_listed_as_a_buyer() {
+    // A system scraped in full with no buyers, so its buyer count is known to be 0 rather than unknown
+    let dir = TempDir::new("keeps_buyers_scraped");
+    let mut conn = db::open(&dir.path.join("keeps_buyers_scraped.db3")).unwrap();
+    let page: PageData = common::parse_fixture("no_buyers_page.html", "TX2270192");
+    db::store_page(&page, page.has_buyers_table(), false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    let seller_page: PageData = common::parse_fixture("seller_page.html", "TX0570004");
+    db::store_page(&seller_page, true, false, &mut conn, &"02-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    db::update_degree_counts(&conn).unwrap();
+    let (buyers_scraped, buyer_count): (Option<bool>, Option<i64>) = 
+        conn.query_row("select buyers_scraped, buyer_count from water_systems where water_system_no = 'TX2270192'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+    assert_eq!(buyers_scraped, Some(true));
+    assert_eq!(buyer_count, Some(0));
+}
+
+#[test]
+fn stores_padded_ws_number_once() {
+    let dir = TempDir::new("padded_ws_number");
+    let mut conn = db::open(&dir.path.join("padded_ws_number.db3")).unwrap();
+    let page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    db::store_page(&page, true, false, &mut conn, &"01-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    // Lists TX2270192 as "tx2270192   ", padded the way the website pads wsnumber in its URLs
+    let padded_page: PageData = common::parse_fixture("padded_buyer_page.html", "TX0570005");
+    db::store_page(&padded_page, true, false, &mut conn, &"02-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    let stored_rows: i64 = 
+        conn.query_row("select count(*) from water_systems where upper(trim(water_system_no)) = 'TX2270192'", [], |row| row.get(0))
+            .unwrap();
+    assert_eq!(stored_rows, 1);
+    let relationships = db::select_relationships_by_seller(&"TX0570005".to_string(), &conn).unwrap();
+    assert_eq!(relationships.len(), 1);
+    assert_eq!(relationships[0].buyer, "TX2270192");
+}
+
+#[test]
+fn rejects_page_without_detail_table() {
+    // Used to panic on the missing name. Now the page is rolled back and nothing is stored.
+    let dir = TempDir::new("rejects_page");
+    let mut conn = db::open(&dir.path.join("rejects_page.db3")).unwrap();
+    let page: PageData = common::parse_fixture("error_page.html", "TX2270192");
+    let mut stored_relationships: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    assert!(db::store_page(&page, false, false, &mut conn, &"01-01-2024".to_string(), &mut stored_relationships).is_err());
+    assert!(stored_relationships.is_empty());
+    assert!(db::select_water_detail(&"TX2270192".to_string(), &conn).unwrap().is_none());
+}
+
+#[test]
+fn upsert_keeps_details_missing_from_the_new_copy() {
+    let dir = TempDir::new("upsert");
+    let mut conn = db::open(&dir.path.join("upsert.db3")).unwrap();
+    let mut page: PageData = common::parse_fixture("detail_page.html", "TX2270192");
+    let mut stored_relationships: std::collections::HashSet<(String, String)> = std::collections::HashSet::new();
+    db::store_page(&page, true, true, &mut conn, &"01-01-2024".to_string(), &mut stored_relationships).unwrap();
+    // i.e., a later copy of the page that is missing some of the details
+    page.water_detail.name = Some("CITY OF TEST RENAMED".to_string());
+    page.water_detail.is_number = None;
+    page.water_detail.county = None;
+    db::store_page(&page, true, true, &mut conn, &"02-01-2024".to_string(), &mut std::collections::HashSet::new()).unwrap();
+    let stored: WaterDetail = db::select_water_details(&conn).unwrap().into_iter().find(|d| d.ws_number == "TX2270192").unwrap();
+    assert_eq!(stored.name.as_deref(), Some("CITY OF TEST RENAMED"));
+    assert_eq!(stored.is_number.as_deref(), Some("5969"));
+    assert_eq!(stored.county.as_deref(), Some("TRAVIS"));
+}
+
+#[test]
+fn marks_each_crawled_system_once() {
+    let dir = TempDir::new("crawl_visited");
+    let conn = db::open(&dir.path.join("crawl_visited.db3")).unwrap();
+    db::create_crawl_visited(&conn).unwrap();
+    assert!(db::mark_crawl_visited("TX2270192", &conn).unwrap());
+    assert!(db::mark_crawl_visited("TX1050013", &conn).unwrap());
+    assert!(!db::mark_crawl_visited("TX2270192", &conn).unwrap());
+}